@@ -0,0 +1,123 @@
+extern crate nalgebra as na;
+
+use na::*;
+use sphere::*;
+use std::cmp::Ordering;
+
+/// Names a sphere owned by a `World`: either a rigid body or a point of a soft body.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BodyRef {
+	Body(usize),
+	// soft body index, point index
+	Point(usize, usize),
+}
+
+#[derive(Debug)]
+struct Proxy {
+	min: Vec3<f32>,
+	max: Vec3<f32>,
+	body: BodyRef,
+}
+
+fn axis(v: &Vec3<f32>, axis: usize) -> f32 {
+	match axis {
+		0 => v.x,
+		1 => v.y,
+		_ => v.z,
+	}
+}
+
+/// Sweep-and-prune broadphase over the bounding boxes of spheres.
+/// Boxes are sorted along the axis their centres spread the most on, and only boxes whose
+/// intervals overlap on that axis are checked on the other two.
+pub struct SweepAndPrune {
+	proxies: Vec<Proxy>,
+}
+
+impl SweepAndPrune {
+	pub fn new() -> SweepAndPrune {
+		SweepAndPrune {
+			proxies: vec![],
+		}
+	}
+
+	pub fn clear(& mut self) {
+		self.proxies.clear();
+	}
+
+	pub fn insert(& mut self, body: BodyRef, sphere: &Sphere) {
+		let extent = Vec3::new(sphere.radius, sphere.radius, sphere.radius);
+		self.push(Proxy {
+			min: sphere.position - extent,
+			max: sphere.position + extent,
+			body: body,
+		});
+	}
+
+	/// Inserts the box covering `sphere` over the last `dt` seconds, from where its velocity says
+	/// it started to where it is now, so fast spheres are paired with anything they passed.
+	pub fn insert_swept(& mut self, body: BodyRef, sphere: &Sphere, dt: f32) {
+		let extent = Vec3::new(sphere.radius, sphere.radius, sphere.radius);
+		let start = sphere.position - sphere.velocity * dt;
+		let min = Vec3::new(start.x.min(sphere.position.x), start.y.min(sphere.position.y), start.z.min(sphere.position.z));
+		let max = Vec3::new(start.x.max(sphere.position.x), start.y.max(sphere.position.y), start.z.max(sphere.position.z));
+		self.push(Proxy {
+			min: min - extent,
+			max: max + extent,
+			body: body,
+		});
+	}
+
+	// A sphere whose position or velocity has blown up to NaN or infinity can't be ordered
+	// against the others, so it is left out of the pairs instead of poisoning the sort.
+	fn push(& mut self, proxy: Proxy) {
+		let finite = (0..3).all(|a| axis(&proxy.min, a).is_finite() && axis(&proxy.max, a).is_finite());
+		if finite {
+			self.proxies.push(proxy);
+		}
+	}
+
+	/// Every pair of inserted spheres whose bounding boxes overlap.
+	pub fn pairs(& mut self) -> Vec<(BodyRef, BodyRef)> {
+		let sort_axis = self.spread_axis();
+		self.proxies.sort_by(|a, b| axis(&a.min, sort_axis).partial_cmp(&axis(&b.min, sort_axis)).unwrap_or(Ordering::Equal));
+
+		let mut pairs = vec![];
+		let mut active: Vec<usize> = vec![];
+		for (index, proxy) in self.proxies.iter().enumerate() {
+			let start = axis(&proxy.min, sort_axis);
+			active.retain(|&other| axis(&self.proxies[other].max, sort_axis) >= start);
+			for &other in active.iter() {
+				let other = &self.proxies[other];
+				let overlaps = (0..3).all(|a| axis(&proxy.min, a) <= axis(&other.max, a) && axis(&other.min, a) <= axis(&proxy.max, a));
+				if overlaps {
+					pairs.push((other.body, proxy.body));
+				}
+			}
+			active.push(index);
+		}
+		pairs
+	}
+
+	fn spread_axis(&self) -> usize {
+		if self.proxies.is_empty() {
+			return 0;
+		}
+		let count = self.proxies.len() as f32;
+		let mut mean: Vec3<f32> = na::zero();
+		let mut mean_sq: Vec3<f32> = na::zero();
+		for proxy in self.proxies.iter() {
+			let centre = (proxy.min + proxy.max) * 0.5;
+			mean = mean + centre / count;
+			mean_sq = mean_sq + centre * centre / count;
+		}
+		let variance = mean_sq - mean * mean;
+		if variance.x >= variance.y && variance.x >= variance.z {
+			0
+		} else if variance.y >= variance.z {
+			1
+		} else {
+			2
+		}
+	}
+}
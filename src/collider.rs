@@ -0,0 +1,360 @@
+extern crate nalgebra as na;
+
+use na::*;
+use collision::*;
+use material::*;
+use sphere::*;
+
+/// Immovable geometry that spheres bounce off, like a `Plane` but of any extent.
+pub trait StaticCollider {
+	fn material(&self) -> &Material;
+
+	/// Closest surface point to a body centre at `position`, which was at `previous` a step ago, and the
+	/// outward normal there. If the centre is inside, or passed through the surface since `previous`,
+	/// the normal points the way back out.
+	fn closest_point(&self, position: Vec3<f32>, previous: Vec3<f32>) -> (Vec3<f32>, Vec3<f32>);
+
+	/// Velocity of the collider's surface at `point`, zero unless it is kinematic.
+	fn velocity_at(&self, _point: Vec3<f32>) -> Vec3<f32> {
+		na::zero()
+	}
+
+	/// Moves the collider to where it is `time` seconds into the simulation, `dt` after the last call.
+	fn advance(& mut self, _time: f32, _dt: f32) {
+	}
+
+	/// Whether the collider moves on its own, so bodies resting on it can't be left asleep.
+	fn is_kinematic(&self) -> bool {
+		false
+	}
+
+	/// Whether `sphere` touches the collider or is within `margin` of it.
+	fn touches(&self, sphere: &Sphere, margin: f32) -> bool {
+		let (point, normal) = self.closest_point(sphere.position, sphere.position);
+		let extent = -na::dot(&sphere.surface_offset(-normal), &normal);
+		na::dot(&(sphere.position - point), &normal) <= extent + margin
+	}
+
+	/// Moves `sphere` out of the collider if it is touching and moving in, returning whether it was.
+	fn check_collision(&self, sphere: &mut Sphere, dt: f32) -> bool {
+		let (point, normal) = self.closest_point(sphere.position, sphere.position - sphere.velocity * dt);
+		if na::dot(&(sphere.velocity - self.velocity_at(point)), &normal) > 0.0 {
+			return false;
+		}
+		let distance = na::dot(&(sphere.position - point), &normal);
+		let extent = -na::dot(&sphere.surface_offset(-normal), &normal);
+		if distance >= extent {
+			return false;
+		}
+		sphere.position = sphere.position + normal * (extent - distance);
+		true
+	}
+
+	/// Outward normal where a sphere moved out by `check_collision` touches the collider.
+	fn normal_at(&self, sphere: &Sphere) -> Vec3<f32> {
+		self.closest_point(sphere.position, sphere.position).1
+	}
+
+	/// Reflects a sphere touching the collider, stopping it instead if it arrives slower than `resting_speed`.
+	fn bounce_sphere(&self, sphere: &mut Sphere, resting_speed: f32) {
+		let normal = self.normal_at(sphere);
+		let surface_velocity = self.velocity_at(sphere.support(-normal));
+		bounce_off(sphere, normal, self.material(), surface_velocity, resting_speed);
+	}
+}
+
+// restitution scales the reflected normal velocity, friction acts on the tangential one, both relative
+// to the surface moving at `surface_velocity`
+// a sphere arriving slower than `resting_speed` is resting on the surface and just stops
+pub fn bounce_off(sphere: &mut Sphere, normal: Vec3<f32>, material: &Material, surface_velocity: Vec3<f32>, resting_speed: f32) {
+	let contact = material.combine(&sphere.material);
+	let normal_speed = na::dot(&(sphere.velocity - surface_velocity), &normal);
+	let restitution = if normal_speed < -resting_speed { contact.restitution } else { 0.0 };
+	let normal_change = -normal_speed * (1.0 + restitution);
+	sphere.velocity = sphere.velocity + normal * normal_change;
+
+	let offset = sphere.contact_offset(-normal);
+	let impulse = friction_impulse(sphere.velocity_at(offset) - surface_velocity, normal, normal_change.abs() * sphere.mass,
+		&contact, |direction| sphere.inverse_mass_at(offset, direction));
+	sphere.apply_impulse(impulse, offset);
+}
+
+// direction from `point` out to `position`, or `fallback` when they coincide
+fn outward(position: Vec3<f32>, point: Vec3<f32>, fallback: Vec3<f32>) -> Vec3<f32> {
+	let offset = position - point;
+	if offset.sqnorm() > 0.0 { offset.normalize() } else { fallback }
+}
+
+// where the step from `previous` to `position` crosses the plane through `origin`, as the fraction
+// of the step and the normal on the side it came from
+fn crossing(position: Vec3<f32>, previous: Vec3<f32>, origin: Vec3<f32>, normal: Vec3<f32>) -> Option<(f32, Vec3<f32>)> {
+	let side = na::dot(&(position - origin), &normal);
+	let previous_side = na::dot(&(previous - origin), &normal);
+	if side * previous_side >= 0.0 {
+		return None;
+	}
+	let fraction = previous_side / (previous_side - side);
+	Some((fraction, if previous_side > 0.0 { normal } else { -normal }))
+}
+
+/// A flat rectangle, solid from both sides, for tables, ramps and walls that end.
+pub struct Quad {
+	centre: Vec3<f32>,
+	// perpendicular, from the centre to the middle of two adjacent edges
+	half_extents: [Vec3<f32>; 2],
+	normal: Vec3<f32>,
+	pub material: Material,
+}
+
+impl Quad {
+	pub fn new(centre: Vec3<f32>, half_width: Vec3<f32>, half_height: Vec3<f32>, material: Material) -> Quad {
+		Quad {
+			centre: centre,
+			half_extents: [half_width, half_height],
+			normal: na::cross(&half_width, &half_height).normalize(),
+			material: material,
+		}
+	}
+
+	// position in units of the half extents, within [-1, 1] on the rectangle
+	fn local(&self, point: Vec3<f32>) -> (f32, f32) {
+		let offset = point - self.centre;
+		let (u, v) = (self.half_extents[0], self.half_extents[1]);
+		(na::dot(&offset, &u) / u.sqnorm(), na::dot(&offset, &v) / v.sqnorm())
+	}
+}
+
+impl StaticCollider for Quad {
+	fn material(&self) -> &Material {
+		&self.material
+	}
+
+	fn closest_point(&self, position: Vec3<f32>, previous: Vec3<f32>) -> (Vec3<f32>, Vec3<f32>) {
+		match crossing(position, previous, self.centre, self.normal) {
+			Some((fraction, normal)) => {
+				let point = previous + (position - previous) * fraction;
+				let (u, v) = self.local(point);
+				if u.abs() <= 1.0 && v.abs() <= 1.0 {
+					return (point, normal);
+				}
+			},
+			None => (),
+		}
+		let (u, v) = self.local(position);
+		let point = self.centre + self.half_extents[0] * u.max(-1.0).min(1.0) + self.half_extents[1] * v.max(-1.0).min(1.0);
+		let facing = if na::dot(&(position - self.centre), &self.normal) < 0.0 { -self.normal } else { self.normal };
+		(point, outward(position, point, facing))
+	}
+}
+
+/// A solid axis aligned box, for obstacles and blocks.
+pub struct Aabb {
+	min: Vec3<f32>,
+	max: Vec3<f32>,
+	pub material: Material,
+}
+
+impl Aabb {
+	pub fn new(min: Vec3<f32>, max: Vec3<f32>, material: Material) -> Aabb {
+		Aabb {
+			min: min,
+			max: max,
+			material: material,
+		}
+	}
+
+	// where the step from `start` to `end` first enters the box, and the outward normal of the face
+	// it enters through; None if it starts inside or misses
+	fn entry(&self, start: Vec3<f32>, end: Vec3<f32>) -> Option<(Vec3<f32>, Vec3<f32>)> {
+		let delta = end - start;
+		let mut enter = 0.0f32;
+		let mut exit = 1.0f32;
+		let mut normal: Option<Vec3<f32>> = None;
+		let slabs = [
+			(start.x, delta.x, self.min.x, self.max.x, Vec3::new(1.0, 0.0, 0.0)),
+			(start.y, delta.y, self.min.y, self.max.y, Vec3::new(0.0, 1.0, 0.0)),
+			(start.z, delta.z, self.min.z, self.max.z, Vec3::new(0.0, 0.0, 1.0)),
+		];
+		for &(from, step, low, high, axis) in slabs.iter() {
+			if step == 0.0 {
+				if from < low || from > high {
+					return None;
+				}
+				continue;
+			}
+			// moving up an axis enters through the low face
+			let (near, far, face) = if step > 0.0 {
+				((low - from) / step, (high - from) / step, -axis)
+			} else {
+				((high - from) / step, (low - from) / step, axis)
+			};
+			if near > enter {
+				enter = near;
+				normal = Some(face);
+			}
+			exit = exit.min(far);
+			if enter > exit {
+				return None;
+			}
+		}
+		normal.map(|normal| (start + delta * enter, normal))
+	}
+}
+
+impl StaticCollider for Aabb {
+	fn material(&self) -> &Material {
+		&self.material
+	}
+
+	fn closest_point(&self, position: Vec3<f32>, previous: Vec3<f32>) -> (Vec3<f32>, Vec3<f32>) {
+		// a centre that entered the box during the step leaves back through the face it came in by,
+		// so fast spheres can't pass through thin boxes or come out of the far side
+		match self.entry(previous, position) {
+			Some(entry) => return entry,
+			None => (),
+		}
+
+		let clamped = Vec3::new(position.x.max(self.min.x).min(self.max.x),
+			position.y.max(self.min.y).min(self.max.y),
+			position.z.max(self.min.z).min(self.max.z));
+		if clamped != position {
+			return (clamped, outward(position, clamped, Vec3::new(0.0, 1.0, 0.0)));
+		}
+
+		// inside: leave through the nearest face
+		let faces = [
+			(position.x - self.min.x, Vec3::new(-1.0, 0.0, 0.0)),
+			(self.max.x - position.x, Vec3::new(1.0, 0.0, 0.0)),
+			(position.y - self.min.y, Vec3::new(0.0, -1.0, 0.0)),
+			(self.max.y - position.y, Vec3::new(0.0, 1.0, 0.0)),
+			(position.z - self.min.z, Vec3::new(0.0, 0.0, -1.0)),
+			(self.max.z - position.z, Vec3::new(0.0, 0.0, 1.0)),
+		];
+		let mut nearest = faces[0];
+		for face in faces.iter() {
+			if face.0 < nearest.0 {
+				nearest = *face;
+			}
+		}
+		let (depth, normal) = nearest;
+		(position + normal * depth, normal)
+	}
+}
+
+/// Closest point to `p` on the triangle `a`, `b`, `c`.
+pub fn closest_on_triangle(p: Vec3<f32>, a: Vec3<f32>, b: Vec3<f32>, c: Vec3<f32>) -> Vec3<f32> {
+	let ab = b - a;
+	let ac = c - a;
+	let ap = p - a;
+	let d1 = na::dot(&ab, &ap);
+	let d2 = na::dot(&ac, &ap);
+	if d1 <= 0.0 && d2 <= 0.0 {
+		return a;
+	}
+
+	let bp = p - b;
+	let d3 = na::dot(&ab, &bp);
+	let d4 = na::dot(&ac, &bp);
+	if d3 >= 0.0 && d4 <= d3 {
+		return b;
+	}
+
+	let vc = d1 * d4 - d3 * d2;
+	if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+		return a + ab * (d1 / (d1 - d3));
+	}
+
+	let cp = p - c;
+	let d5 = na::dot(&ab, &cp);
+	let d6 = na::dot(&ac, &cp);
+	if d6 >= 0.0 && d5 <= d6 {
+		return c;
+	}
+
+	let vb = d5 * d2 - d1 * d6;
+	if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+		return a + ac * (d2 / (d2 - d6));
+	}
+
+	let va = d3 * d6 - d5 * d4;
+	if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+		return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+	}
+
+	let denominator = 1.0 / (va + vb + vc);
+	a + ab * (vb * denominator) + ac * (vc * denominator)
+}
+
+/// Static triangles, each solid from both sides, for ramps and uneven ground.
+pub struct TriangleMesh {
+	vertices: Vec<Vec3<f32>>,
+	triangles: Vec<[usize; 3]>,
+	pub material: Material,
+}
+
+impl TriangleMesh {
+	pub fn new(vertices: Vec<Vec3<f32>>, triangles: Vec<[usize; 3]>, material: Material) -> TriangleMesh {
+		TriangleMesh {
+			vertices: vertices,
+			triangles: triangles,
+			material: material,
+		}
+	}
+
+	fn corners(&self, triangle: &[usize; 3]) -> (Vec3<f32>, Vec3<f32>, Vec3<f32>) {
+		(self.vertices[triangle[0]], self.vertices[triangle[1]], self.vertices[triangle[2]])
+	}
+}
+
+impl StaticCollider for TriangleMesh {
+	fn material(&self) -> &Material {
+		&self.material
+	}
+
+	fn closest_point(&self, position: Vec3<f32>, previous: Vec3<f32>) -> (Vec3<f32>, Vec3<f32>) {
+		// the first triangle passed through during the step wins
+		let mut first_crossing: Option<(f32, Vec3<f32>, Vec3<f32>)> = None;
+		for triangle in self.triangles.iter() {
+			let (a, b, c) = self.corners(triangle);
+			let normal = na::cross(&(b - a), &(c - a));
+			if normal.sqnorm() == 0.0 {
+				continue;
+			}
+			match crossing(position, previous, a, normal.normalize()) {
+				Some((fraction, normal)) => {
+					let point = previous + (position - previous) * fraction;
+					let inside = (closest_on_triangle(point, a, b, c) - point).sqnorm() < 1.0e-8;
+					let earlier = first_crossing.map_or(true, |(first, _, _)| fraction < first);
+					if inside && earlier {
+						first_crossing = Some((fraction, point, normal));
+					}
+				},
+				None => (),
+			}
+		}
+		match first_crossing {
+			Some((_, point, normal)) => return (point, normal),
+			None => (),
+		}
+
+		let mut nearest: Option<(f32, Vec3<f32>, Vec3<f32>)> = None;
+		for triangle in self.triangles.iter() {
+			let (a, b, c) = self.corners(triangle);
+			let point = closest_on_triangle(position, a, b, c);
+			let distance = (position - point).sqnorm();
+			if nearest.map_or(true, |(best, _, _)| distance < best) {
+				let mut facing = na::cross(&(b - a), &(c - a));
+				if na::dot(&(position - a), &facing) < 0.0 {
+					facing = -facing;
+				}
+				let facing = if facing.sqnorm() > 0.0 { facing.normalize() } else { Vec3::new(0.0, 1.0, 0.0) };
+				nearest = Some((distance, point, outward(position, point, facing)));
+			}
+		}
+		match nearest {
+			Some((_, point, normal)) => (point, normal),
+			// an empty mesh has nothing to touch, and a zero normal never collides
+			None => (position, na::zero()),
+		}
+	}
+}
@@ -0,0 +1,177 @@
+extern crate nalgebra as na;
+
+use na::*;
+use gjk;
+use material::*;
+use shape::*;
+use sphere::*;
+use vm::*;
+
+// multiple of the speed gravity adds in one step that still counts as resting
+const RESTING_STEPS: f32 = 2.0;
+
+/// Approach speed in m/s below which a contact is treated as resting: it stops instead of bouncing,
+/// so restitution can't keep feeding back the velocity gravity `g` adds over each step of `dt` seconds.
+pub fn resting_speed(g: f32, dt: f32) -> f32 {
+	RESTING_STEPS * g.abs() * dt
+}
+
+#[derive(Debug)]
+pub struct CollisionResult {
+	pub normal: Vec3<f32>,
+	pub mtv: Vec3<f32>,
+	// world space point the bodies touch at, where contact impulses act
+	pub point: Vec3<f32>,
+}
+
+// the normal response comes from the collision law, the tangential one is Coulomb friction
+// between the contact points and is what sets the spheres spinning
+pub fn resolve_collision(lhs: & mut Sphere, rhs: & mut Sphere, res: CollisionResult, mac: & VM) -> () {
+	let lhs_offset = res.point - lhs.position;
+	let rhs_offset = res.point - rhs.position;
+	separate(lhs, rhs, &res);
+
+	let contact = lhs.material.combine(&rhs.material);
+	let (d_s_f_lhs, d_s_f_rhs) = law_speed_changes(lhs, rhs, res.normal, &contact, mac);
+
+	let d_v_f_lhs = res.normal * d_s_f_lhs;
+	let d_v_f_rhs = res.normal * d_s_f_rhs;
+
+	lhs.velocity = lhs.velocity + d_v_f_lhs;
+	rhs.velocity = rhs.velocity + d_v_f_rhs;
+
+	let normal_impulse = (d_s_f_lhs * lhs.mass).abs();
+	let impulse = friction_impulse(lhs.velocity_at(lhs_offset) - rhs.velocity_at(rhs_offset), res.normal,
+		normal_impulse, &contact, |direction| {
+		lhs.inverse_mass_at(lhs_offset, direction) + rhs.inverse_mass_at(rhs_offset, direction)
+	});
+	lhs.apply_impulse(impulse, lhs_offset);
+	rhs.apply_impulse(-impulse, rhs_offset);
+}
+
+/// Changes in the speed of `lhs` and `rhs` along `normal` that the collision law asks for.
+pub fn law_speed_changes(lhs: & Sphere, rhs: & Sphere, normal: Vec3<f32>, contact: &ContactMaterial, mac: & VM) -> (f32, f32) {
+	let p_lhs = na::dot(&lhs.velocity, &normal) * lhs.mass;
+	let p_rhs = na::dot(&rhs.velocity, &normal) * rhs.mass;
+
+	// p other_p mass restitution other_mass
+	let data = vec![p_lhs as f64, p_rhs as f64, lhs.mass as f64, contact.restitution as f64, rhs.mass as f64];
+	let d_s_f_lhs = mac.run(&data) as f32;
+
+	let data = vec![p_rhs as f64, p_lhs as f64, rhs.mass as f64, contact.restitution as f64, lhs.mass as f64];
+	let d_s_f_rhs = mac.run(&data) as f32;
+
+	(d_s_f_lhs, d_s_f_rhs)
+}
+
+/// Separating speed along `normal` after the collision law has acted on `lhs` and `rhs`.
+pub fn law_separating_speed(lhs: & Sphere, rhs: & Sphere, normal: Vec3<f32>, contact: &ContactMaterial, mac: & VM) -> f32 {
+	let (d_lhs, d_rhs) = law_speed_changes(lhs, rhs, normal, contact, mac);
+	na::dot(&(lhs.velocity - rhs.velocity), &normal) + d_lhs - d_rhs
+}
+
+/// Pushes two overlapping spheres apart along the mtv, the larger sphere moving further.
+pub fn separate(lhs: & mut Sphere, rhs: & mut Sphere, res: &CollisionResult) {
+	let total_radius = lhs.radius + rhs.radius;
+	lhs.position = lhs.position + res.mtv * (lhs.radius / total_radius);
+	rhs.position = rhs.position - res.mtv * (rhs.radius / total_radius);
+}
+
+/// Coulomb friction impulse on the body whose contact point moves at `relative_velocity` relative to the other.
+/// The contact sticks if stopping the slide takes no more than the static friction times `normal_impulse`,
+/// otherwise it slides against the dynamic friction times `normal_impulse`.
+/// `inverse_mass` gives the combined `Sphere::inverse_mass_at` of both bodies along a direction.
+pub fn friction_impulse<F>(relative_velocity: Vec3<f32>, normal: Vec3<f32>, normal_impulse: f32,
+	contact: &ContactMaterial, inverse_mass: F) -> Vec3<f32> where F: Fn(Vec3<f32>) -> f32 {
+	clamp_friction(stopping_impulse(relative_velocity, normal, inverse_mass), normal_impulse, contact)
+}
+
+/// Impulse along the contact plane that would stop the slip of `relative_velocity`, zero if nothing can.
+pub fn stopping_impulse<F>(relative_velocity: Vec3<f32>, normal: Vec3<f32>, inverse_mass: F) -> Vec3<f32>
+	where F: Fn(Vec3<f32>) -> f32 {
+	let slip = relative_velocity - normal * na::dot(&relative_velocity, &normal);
+	let speed = slip.norm();
+	if speed == 0.0 {
+		return na::zero();
+	}
+	let direction = slip / speed;
+	let inverse_mass = inverse_mass(direction);
+	if inverse_mass <= 0.0 {
+		return na::zero();
+	}
+	-direction * (speed / inverse_mass)
+}
+
+/// Limits a friction impulse to the Coulomb cone: kept whole within the static friction times
+/// `normal_impulse`, otherwise cut down to the dynamic friction times it.
+pub fn clamp_friction(impulse: Vec3<f32>, normal_impulse: f32, contact: &ContactMaterial) -> Vec3<f32> {
+	let magnitude = impulse.norm();
+	if magnitude <= contact.static_friction * normal_impulse {
+		impulse
+	} else {
+		impulse * (contact.dynamic_friction * normal_impulse / magnitude).min(1.0)
+	}
+}
+
+/// Overlap of two bodies, pushing `lhs` out of `rhs`. Balls are tested directly, any other pair of shapes with GJK/EPA.
+pub fn hit_test(lhs: & Sphere, rhs: & Sphere) -> Option<CollisionResult> {
+
+	let dist = (lhs.position - rhs.position).norm();
+	if dist > lhs.radius + rhs.radius {
+		return None;
+	}
+	match (&lhs.shape, &rhs.shape) {
+		(&Shape::Ball(_), &Shape::Ball(_)) => (),
+		_ => return gjk::penetration(lhs, rhs),
+	}
+
+	// Coincident centres give no direction to push apart along, so pick a fixed one.
+	let contact_normal = if dist > 0.0 {
+		(lhs.position - rhs.position) / dist
+	} else {
+		Vec3::new(0.0, 1.0, 0.0)
+	};
+	let mtv = contact_normal * (lhs.radius + rhs.radius - dist);
+	let on_lhs = lhs.position - contact_normal * lhs.radius;
+	let on_rhs = rhs.position + contact_normal * rhs.radius;
+
+	let result = CollisionResult {
+		normal: contact_normal,
+		mtv: mtv,
+		point: (on_lhs + on_rhs) * 0.5,
+	};
+	Some(result)
+}
+
+/// Time into a step of `dt` seconds at which two spheres, moving in straight lines to where they are now,
+/// first touched. None if they never touched during the step or already overlapped at its start.
+pub fn time_of_impact(lhs: & Sphere, rhs: & Sphere, dt: f32) -> Option<f32> {
+	let velocity = lhs.velocity - rhs.velocity;
+	first_touch(lhs.position - rhs.position - velocity * dt, velocity, lhs.radius + rhs.radius, dt)
+}
+
+/// Time from now at which two spheres, carrying on in straight lines at their current velocities,
+/// will first touch. None if they don't within `window` seconds or already overlap.
+pub fn time_until_impact(lhs: & Sphere, rhs: & Sphere, window: f32) -> Option<f32> {
+	first_touch(lhs.position - rhs.position, lhs.velocity - rhs.velocity, lhs.radius + rhs.radius, window)
+}
+
+// earliest time in [0, window] at which `start + velocity * t` is `radius` long, if it starts longer
+fn first_touch(start: Vec3<f32>, velocity: Vec3<f32>, radius: f32, window: f32) -> Option<f32> {
+	let a = na::dot(&velocity, &velocity);
+	let b = 2.0 * na::dot(&start, &velocity);
+	let c = na::dot(&start, &start) - radius * radius;
+	if c <= 0.0 || a == 0.0 {
+		return None;
+	}
+	let discriminant = b * b - 4.0 * a * c;
+	if discriminant < 0.0 {
+		return None;
+	}
+	let time = (-b - discriminant.sqrt()) / (2.0 * a);
+	if time >= 0.0 && time <= window {
+		Some(time)
+	} else {
+		None
+	}
+}
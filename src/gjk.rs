@@ -0,0 +1,367 @@
+extern crate nalgebra as na;
+
+use na::*;
+use collision::*;
+use sphere::*;
+
+const MAX_ITERATIONS: usize = 64;
+// GJK and EPA stop once a new support point gets less than this much closer to the surface, in m
+const TOLERANCE: f32 = 1.0e-4;
+// share of the lengths a direction was built from below which it is only rounding error
+const EPSILON: f32 = 1.0e-5;
+
+// a point of the Minkowski difference lhs - rhs, with the surface points of each body it came from
+#[derive(Clone, Copy, Debug)]
+struct SupportPoint {
+	point: Vec3<f32>,
+	lhs: Vec3<f32>,
+	rhs: Vec3<f32>,
+}
+
+// point of the Minkowski difference lhs - rhs furthest along `direction`
+fn support(lhs: &Sphere, rhs: &Sphere, direction: Vec3<f32>) -> SupportPoint {
+	let on_lhs = lhs.support(direction);
+	let on_rhs = rhs.support(-direction);
+	SupportPoint {
+		point: on_lhs - on_rhs,
+		lhs: on_lhs,
+		rhs: on_rhs,
+	}
+}
+
+fn triple(a: Vec3<f32>, b: Vec3<f32>, c: Vec3<f32>) -> Vec3<f32> {
+	na::cross(&na::cross(&a, &b), &c)
+}
+
+// whether `v`, built from vectors whose lengths multiply to `scale`, is zero but for rounding
+fn negligible(v: Vec3<f32>, scale: f32) -> bool {
+	v.sqnorm() <= EPSILON * EPSILON * scale * scale
+}
+
+fn perpendicular(v: Vec3<f32>) -> Vec3<f32> {
+	if v.x.abs() < 0.57 {
+		na::cross(&v, &Vec3::new(1.0, 0.0, 0.0))
+	} else {
+		na::cross(&v, &Vec3::new(0.0, 1.0, 0.0))
+	}
+}
+
+/// Overlap of two convex bodies, found by GJK and measured by EPA.
+/// As with `hit_test` the normal points from `rhs` to `lhs`, and moving `lhs` by the mtv separates them.
+pub fn penetration(lhs: &Sphere, rhs: &Sphere) -> Option<CollisionResult> {
+	let mut direction = lhs.position - rhs.position;
+	if direction.sqnorm() == 0.0 {
+		direction = Vec3::new(1.0, 0.0, 0.0);
+	}
+	let first = support(lhs, rhs, direction);
+	let mut simplex = vec![first];
+	direction = -first.point;
+
+	for _ in 0..MAX_ITERATIONS {
+		// the origin sits on the difference's surface: the bodies only touch
+		if direction.sqnorm() == 0.0 {
+			return None;
+		}
+		let next = support(lhs, rhs, direction);
+		let reach = na::dot(&next.point, &direction);
+		if reach <= 0.0 {
+			return None;
+		}
+		// no nearer the origin than the simplex already got: the origin is within TOLERANCE of the surface
+		let furthest = simplex.iter().fold(::std::f32::NEG_INFINITY, |f, p| f.max(na::dot(&p.point, &direction)));
+		if reach - furthest <= TOLERANCE * direction.norm() {
+			return None;
+		}
+		simplex.push(next);
+		if contains_origin(& mut simplex, & mut direction) {
+			return Some(expand(lhs, rhs, simplex));
+		}
+	}
+	// rounding has GJK circling the origin without enclosing it, so the bodies overlap at most a little
+	Some(centre_axis_overlap(lhs, rhs))
+}
+
+// overlap along the line between the centres: moving lhs by the mtv separates the bodies along
+// that axis, though not always by the shortest way
+fn centre_axis_overlap(lhs: &Sphere, rhs: &Sphere) -> CollisionResult {
+	let offset = lhs.position - rhs.position;
+	let normal = if offset.sqnorm() > 0.0 { offset.normalize() } else { Vec3::new(0.0, 1.0, 0.0) };
+	let deepest = support(lhs, rhs, -normal);
+	let depth = (-na::dot(&deepest.point, &normal)).max(0.0);
+	CollisionResult {
+		normal: normal,
+		mtv: normal * depth,
+		point: (deepest.lhs + deepest.rhs) * 0.5,
+	}
+}
+
+// the newest point is last; shrinks the simplex to the feature nearest the origin and aims `direction` at it
+fn contains_origin(simplex: & mut Vec<SupportPoint>, direction: & mut Vec3<f32>) -> bool {
+	match simplex.len() {
+		2 => {
+			line(simplex, direction);
+			false
+		},
+		3 => {
+			triangle(simplex, direction);
+			false
+		},
+		_ => tetrahedron(simplex, direction),
+	}
+}
+
+fn line(simplex: & mut Vec<SupportPoint>, direction: & mut Vec3<f32>) {
+	let (b, a) = (simplex[0], simplex[1]);
+	let ab = b.point - a.point;
+	let ao = -a.point;
+	if na::dot(&ab, &ao) > 0.0 {
+		let towards = triple(ab, ao, ab);
+		// the origin is on the segment, any side will do
+		*direction = if negligible(towards, ab.sqnorm() * ao.norm()) { perpendicular(ab) } else { towards };
+	} else {
+		*simplex = vec![a];
+		*direction = ao;
+	}
+}
+
+fn triangle(simplex: & mut Vec<SupportPoint>, direction: & mut Vec3<f32>) {
+	let (c, b, a) = (simplex[0], simplex[1], simplex[2]);
+	let ab = b.point - a.point;
+	let ac = c.point - a.point;
+	let ao = -a.point;
+	let abc = na::cross(&ab, &ac);
+
+	if negligible(abc, ab.norm() * ac.norm()) {
+		*simplex = vec![b, a];
+		line(simplex, direction);
+	} else if na::dot(&na::cross(&abc, &ac), &ao) > 0.0 {
+		if na::dot(&ac, &ao) > 0.0 {
+			*simplex = vec![c, a];
+			let towards = triple(ac, ao, ac);
+			*direction = if negligible(towards, ac.sqnorm() * ao.norm()) { perpendicular(ac) } else { towards };
+		} else {
+			*simplex = vec![b, a];
+			line(simplex, direction);
+		}
+	} else if na::dot(&na::cross(&ab, &abc), &ao) > 0.0 {
+		*simplex = vec![b, a];
+		line(simplex, direction);
+	} else if na::dot(&abc, &ao) > 0.0 {
+		*direction = abc;
+	} else {
+		*simplex = vec![b, c, a];
+		*direction = -abc;
+	}
+}
+
+fn tetrahedron(simplex: & mut Vec<SupportPoint>, direction: & mut Vec3<f32>) -> bool {
+	let (d, c, b, a) = (simplex[0], simplex[1], simplex[2], simplex[3]);
+	let ao = -a.point;
+	// each face with a, the vertex left out, and the order the triangle case expects
+	let faces = [(b, c, d, vec![c, b, a]), (c, d, b, vec![d, c, a]), (d, b, c, vec![b, d, a])];
+	for &(p, q, opposite, ref reduced) in faces.iter() {
+		let mut normal = na::cross(&(p.point - a.point), &(q.point - a.point));
+		if na::dot(&normal, &(opposite.point - a.point)) > 0.0 {
+			normal = -normal;
+		}
+		if na::dot(&normal, &ao) > 0.0 {
+			*simplex = reduced.clone();
+			triangle(simplex, direction);
+			return false;
+		}
+	}
+	true
+}
+
+fn face_normal(points: &[SupportPoint], face: &[usize; 3]) -> Vec3<f32> {
+	na::cross(&(points[face[1]].point - points[face[0]].point), &(points[face[2]].point - points[face[0]].point))
+}
+
+// weights of the face's corners that put them at `p`, which lies in the face's plane
+fn barycentric(points: &[SupportPoint], face: &[usize; 3], p: Vec3<f32>) -> (f32, f32, f32) {
+	let a = points[face[0]].point;
+	let ab = points[face[1]].point - a;
+	let ac = points[face[2]].point - a;
+	let ap = p - a;
+	let (d00, d01, d11) = (na::dot(&ab, &ab), na::dot(&ab, &ac), na::dot(&ac, &ac));
+	let (d20, d21) = (na::dot(&ap, &ab), na::dot(&ap, &ac));
+	let denominator = d00 * d11 - d01 * d01;
+	if denominator == 0.0 {
+		return (1.0, 0.0, 0.0);
+	}
+	let v = (d11 * d20 - d01 * d21) / denominator;
+	let w = (d00 * d21 - d01 * d20) / denominator;
+	(1.0 - v - w, v, w)
+}
+
+// grows the tetrahedron GJK ended with towards the surface of the Minkowski difference
+// until its face nearest the origin is on that surface, giving the shortest way out
+fn expand(lhs: &Sphere, rhs: &Sphere, simplex: Vec<SupportPoint>) -> CollisionResult {
+	let mut points = simplex;
+	let mut faces: Vec<[usize; 3]> = vec![[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]];
+	let centre = (points[0].point + points[1].point + points[2].point + points[3].point) / 4.0;
+	for face in faces.iter_mut() {
+		if na::dot(&face_normal(&points, face), &(points[face[0]].point - centre)) < 0.0 {
+			face.swap(1, 2);
+		}
+	}
+
+	let mut best_face = faces[0];
+	let mut best_normal: Vec3<f32> = na::zero();
+	let mut best_distance = 0.0;
+	for _ in 0..MAX_ITERATIONS {
+		let mut closest = None;
+		let mut min_distance = ::std::f32::INFINITY;
+		for face in faces.iter() {
+			let normal = face_normal(&points, face);
+			let length = normal.norm();
+			if length == 0.0 {
+				continue;
+			}
+			let normal = normal / length;
+			let distance = na::dot(&normal, &points[face[0]].point);
+			if distance < min_distance {
+				min_distance = distance;
+				closest = Some((*face, normal));
+			}
+		}
+		let (face, normal) = match closest {
+			Some(closest) => closest,
+			None => break,
+		};
+		best_face = face;
+		best_normal = normal;
+		best_distance = min_distance.max(0.0);
+
+		let next = support(lhs, rhs, normal);
+		if na::dot(&next.point, &normal) - min_distance < TOLERANCE {
+			break;
+		}
+
+		// faces that can see the new point are replaced by a fan from it over the hole's rim
+		let mut rim: Vec<(usize, usize)> = vec![];
+		let mut kept = vec![];
+		for face in faces.iter() {
+			if na::dot(&face_normal(&points, face), &(next.point - points[face[0]].point)) > 0.0 {
+				for &(a, b) in [(face[0], face[1]), (face[1], face[2]), (face[2], face[0])].iter() {
+					match rim.iter().position(|&edge| edge == (b, a)) {
+						Some(shared) => { rim.swap_remove(shared); },
+						None => rim.push((a, b)),
+					}
+				}
+			} else {
+				kept.push(*face);
+			}
+		}
+		points.push(next);
+		let apex = points.len() - 1;
+		faces = kept;
+		for (a, b) in rim {
+			faces.push([a, b, apex]);
+		}
+	}
+
+	// the origin's projection onto the closest face, carried back to each body's surface,
+	// gives a touching point on each; the contact is midway between them
+	let (u, v, w) = barycentric(&points, &best_face, best_normal * best_distance);
+	let (a, b, c) = (points[best_face[0]], points[best_face[1]], points[best_face[2]]);
+	let on_lhs = a.lhs * u + b.lhs * v + c.lhs * w;
+	let on_rhs = a.rhs * u + b.rhs * v + c.rhs * w;
+
+	CollisionResult {
+		normal: -best_normal,
+		mtv: -best_normal * best_distance,
+		point: (on_lhs + on_rhs) * 0.5,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use na::*;
+	use collision::*;
+	use shape::*;
+	use sphere::*;
+	use super::penetration;
+
+	// EPA only gets within TOLERANCE of curved surfaces, which leaves their normals this far out
+	const CURVED: f32 = 2.0e-2;
+	const FLAT: f32 = 1.0e-3;
+
+	fn body(shape: Shape, position: Vec3<f32>) -> Sphere {
+		let mut body = Sphere::with_shape(shape, 1.0);
+		body.position = position;
+		body
+	}
+
+	fn cube_hull(half: f32) -> Shape {
+		let mut vertices = vec![];
+		for &x in [-half, half].iter() {
+			for &y in [-half, half].iter() {
+				for &z in [-half, half].iter() {
+					vertices.push(Vec3::new(x, y, z));
+				}
+			}
+		}
+		Shape::ConvexHull(Hull::new(vertices).unwrap())
+	}
+
+	fn octahedron_hull(half: f32) -> Shape {
+		Shape::ConvexHull(Hull::new(vec![
+			Vec3::new(half, 0.0, 0.0), Vec3::new(-half, 0.0, 0.0),
+			Vec3::new(0.0, half, 0.0), Vec3::new(0.0, -half, 0.0),
+			Vec3::new(0.0, 0.0, half), Vec3::new(0.0, 0.0, -half),
+		]).unwrap())
+	}
+
+	fn assert_contact(result: Option<CollisionResult>, normal: Vec3<f32>, depth: f32, normal_tolerance: f32) {
+		let result = result.expect("bodies should overlap");
+		assert!((result.normal - normal).norm() < normal_tolerance, "normal {:?} is not {:?}", result.normal, normal);
+		assert!((result.mtv.norm() - depth).abs() < FLAT, "depth {} is not {}", result.mtv.norm(), depth);
+		assert!(na::dot(&result.mtv, &normal) > 0.0, "mtv {:?} points away from {:?}", result.mtv, normal);
+	}
+
+	#[test]
+	fn box_box() {
+		let lhs = body(Shape::Cuboid(Vec3::new(0.5, 0.5, 0.5)), Vec3::new(0.2, 0.9, 0.1));
+		let rhs = body(Shape::Cuboid(Vec3::new(0.5, 0.5, 0.5)), na::zero());
+		assert_contact(penetration(&lhs, &rhs), Vec3::new(0.0, 1.0, 0.0), 0.1, FLAT);
+	}
+
+	#[test]
+	fn box_box_apart() {
+		let lhs = body(Shape::Cuboid(Vec3::new(0.5, 0.5, 0.5)), Vec3::new(0.2, 1.1, 0.1));
+		let rhs = body(Shape::Cuboid(Vec3::new(0.5, 0.5, 0.5)), na::zero());
+		assert!(penetration(&lhs, &rhs).is_none());
+	}
+
+	#[test]
+	fn ball_box() {
+		let lhs = body(Shape::Ball(0.5), Vec3::new(0.1, 0.9, 0.0));
+		let rhs = body(Shape::Cuboid(Vec3::new(0.5, 0.5, 0.5)), na::zero());
+		assert_contact(penetration(&lhs, &rhs), Vec3::new(0.0, 1.0, 0.0), 0.1, CURVED);
+	}
+
+	#[test]
+	fn capsule_capsule() {
+		let lhs = body(Shape::Capsule(0.5, 0.25), Vec3::new(0.4, 0.2, 0.0));
+		let rhs = body(Shape::Capsule(0.5, 0.25), na::zero());
+		assert_contact(penetration(&lhs, &rhs), Vec3::new(1.0, 0.0, 0.0), 0.1, CURVED);
+	}
+
+	#[test]
+	fn hull_box() {
+		let lhs = body(octahedron_hull(0.5), Vec3::new(0.0, 0.9, 0.0));
+		let rhs = body(Shape::Cuboid(Vec3::new(0.5, 0.5, 0.5)), na::zero());
+		let result = penetration(&lhs, &rhs).unwrap();
+		// the octahedron's lowest vertex pokes into the middle of the box's top face
+		assert!((result.point - Vec3::new(0.0, 0.45, 0.0)).norm() < FLAT, "point {:?}", result.point);
+		assert_contact(Some(result), Vec3::new(0.0, 1.0, 0.0), 0.1, FLAT);
+	}
+
+	#[test]
+	fn hull_hull() {
+		let lhs = body(cube_hull(0.5), Vec3::new(0.1, 0.2, 0.95));
+		let rhs = body(cube_hull(0.5), na::zero());
+		assert_contact(penetration(&lhs, &rhs), Vec3::new(0.0, 0.0, 1.0), 0.05, FLAT);
+	}
+}
@@ -0,0 +1,74 @@
+extern crate nalgebra as na;
+
+use na::*;
+
+/// Numerical scheme used to advance positions and velocities over a time step.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Integrator {
+	SymplecticEuler,
+	VelocityVerlet,
+	RungeKutta4,
+}
+use self::Integrator::*;
+
+fn offset(base: &[Vec3<f32>], delta: &[Vec3<f32>], scale: f32) -> Vec<Vec3<f32>> {
+	base.iter().zip(delta.iter()).map(|(b, d)| *b + *d * scale).collect()
+}
+
+impl Integrator {
+	/// Looks up an integrator by the name used in `eq.txt`.
+	pub fn from_name(name: &str) -> Option<Integrator> {
+		match name {
+			"symplectic_euler" => Some(SymplecticEuler),
+			"velocity_verlet" => Some(VelocityVerlet),
+			"runge_kutta" => Some(RungeKutta4),
+			_ => None,
+		}
+	}
+
+	/// Advances `positions` and `velocities` by `dt`.
+	/// `accel` returns the acceleration of every particle for a given set of positions and velocities,
+	/// and may be evaluated several times per step depending on the scheme.
+	pub fn integrate<F>(&self, positions: & mut [Vec3<f32>], velocities: & mut [Vec3<f32>], dt: f32, mut accel: F)
+		where F: FnMut(&[Vec3<f32>], &[Vec3<f32>]) -> Vec<Vec3<f32>> {
+		match *self {
+			SymplecticEuler => {
+				let a = accel(positions, velocities);
+				for i in 0..positions.len() {
+					velocities[i] = velocities[i] + a[i] * dt;
+					positions[i] = positions[i] + velocities[i] * dt;
+				}
+			},
+			VelocityVerlet => {
+				let a = accel(positions, velocities);
+				for i in 0..positions.len() {
+					positions[i] = positions[i] + velocities[i] * dt + a[i] * (0.5 * dt * dt);
+				}
+				// velocity dependent forces (damping) are evaluated at a first order velocity guess
+				let predicted = offset(velocities, &a, dt);
+				let next_a = accel(positions, &predicted);
+				for i in 0..positions.len() {
+					velocities[i] = velocities[i] + (a[i] + next_a[i]) * (0.5 * dt);
+				}
+			},
+			RungeKutta4 => {
+				let k1_x = velocities.to_vec();
+				let k1_v = accel(positions, velocities);
+
+				let k2_x = offset(velocities, &k1_v, dt / 2.0);
+				let k2_v = accel(&offset(positions, &k1_x, dt / 2.0), &k2_x);
+
+				let k3_x = offset(velocities, &k2_v, dt / 2.0);
+				let k3_v = accel(&offset(positions, &k2_x, dt / 2.0), &k3_x);
+
+				let k4_x = offset(velocities, &k3_v, dt);
+				let k4_v = accel(&offset(positions, &k3_x, dt), &k4_x);
+
+				for i in 0..positions.len() {
+					positions[i] = positions[i] + (k1_x[i] + k2_x[i] * 2.0 + k3_x[i] * 2.0 + k4_x[i]) * (dt / 6.0);
+					velocities[i] = velocities[i] + (k1_v[i] + k2_v[i] * 2.0 + k3_v[i] * 2.0 + k4_v[i]) * (dt / 6.0);
+				}
+			},
+		}
+	}
+}
@@ -0,0 +1,98 @@
+extern crate nalgebra as na;
+
+use std::f32::consts::PI;
+
+use na::*;
+use collider::*;
+use material::*;
+use sphere::*;
+
+/// A static collider moved by a script, like a moving floor or a rotating paddle.
+/// `motion` gives the transform of the wrapped collider, described in its own frame, at any time
+/// in seconds since the world started. Spheres bounce off it relative to its surface's velocity.
+pub struct Kinematic<C> {
+	collider: C,
+	motion: Box<Fn(f32) -> Iso3<f32>>,
+	transform: Iso3<f32>,
+	previous: Iso3<f32>,
+	velocity: Vec3<f32>,
+	angular_velocity: Vec3<f32>,
+}
+
+// position relative to `transform`
+fn to_local(transform: &Iso3<f32>, position: Vec3<f32>) -> Vec3<f32> {
+	na::transpose(transform.rotation.submat()) * (position - transform.translation)
+}
+
+impl<C: StaticCollider> Kinematic<C> {
+	pub fn new<F>(collider: C, motion: F) -> Kinematic<C> where F: Fn(f32) -> Iso3<f32> + 'static {
+		let transform = motion(0.0);
+		Kinematic {
+			collider: collider,
+			motion: Box::new(motion),
+			transform: transform,
+			previous: transform,
+			velocity: na::zero(),
+			angular_velocity: na::zero(),
+		}
+	}
+
+	/// Slides `origin` back and forth by up to `amplitude` once every `period` seconds.
+	pub fn oscillating(collider: C, origin: Vec3<f32>, amplitude: Vec3<f32>, period: f32) -> Kinematic<C> {
+		Kinematic::new(collider, move |time| {
+			Iso3::new(origin + amplitude * (2.0 * PI * time / period).sin(), na::zero())
+		})
+	}
+
+	/// Turns about `origin` at a constant `angular_velocity`, in radians per second about its direction.
+	pub fn spinning(collider: C, origin: Vec3<f32>, angular_velocity: Vec3<f32>) -> Kinematic<C> {
+		Kinematic::new(collider, move |time| Iso3::new(origin, angular_velocity * time))
+	}
+
+	pub fn transform(&self) -> &Iso3<f32> {
+		&self.transform
+	}
+
+	fn to_world(&self, (point, normal): (Vec3<f32>, Vec3<f32>)) -> (Vec3<f32>, Vec3<f32>) {
+		let rotation = *self.transform.rotation.submat();
+		(rotation * point + self.transform.translation, rotation * normal)
+	}
+}
+
+impl<C: StaticCollider> StaticCollider for Kinematic<C> {
+	fn material(&self) -> &Material {
+		self.collider.material()
+	}
+
+	// the centre's previous position is taken against where the collider was then, so a collider
+	// sweeping past a sphere is caught like a sphere tunnelling through it
+	fn closest_point(&self, position: Vec3<f32>, previous: Vec3<f32>) -> (Vec3<f32>, Vec3<f32>) {
+		let local = self.collider.closest_point(to_local(&self.transform, position), to_local(&self.previous, previous));
+		self.to_world(local)
+	}
+
+	fn normal_at(&self, sphere: &Sphere) -> Vec3<f32> {
+		let position = to_local(&self.transform, sphere.position);
+		self.to_world(self.collider.closest_point(position, position)).1
+	}
+
+	fn velocity_at(&self, point: Vec3<f32>) -> Vec3<f32> {
+		self.velocity + na::cross(&self.angular_velocity, &(point - self.transform.translation))
+	}
+
+	fn is_kinematic(&self) -> bool {
+		true
+	}
+
+	fn advance(& mut self, time: f32, dt: f32) {
+		self.previous = self.transform;
+		self.transform = (self.motion)(time);
+		// no time passed to measure the motion over, so the last velocities stand
+		if dt <= 0.0 {
+			return;
+		}
+		self.velocity = (self.transform.translation - self.previous.translation) / dt;
+		let turn = self.transform.rotation * na::inv(&self.previous.rotation).unwrap();
+		self.angular_velocity = turn.rotation() / dt;
+	}
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use integrator::*;
+use parser::*;
+use vm::*;
+
+/// Constants and compiled expressions that drive the simulation, normally read from `eq.txt`.
+pub struct Laws {
+	// spring stiffness, N/m
+	pub k: f32,
+	// vertical acceleration, m/s^2
+	pub g: f32,
+	// spring damping, N*s/m
+	pub dampening: f32,
+	// bulk modulus of soft body volume constraints, Pa
+	pub volume_stiffness: f32,
+	pub restitution: f32,
+	pub integrator: Integrator,
+	// length of a fixed step in seconds
+	pub dt: f32,
+	// world steps each fixed step is split into
+	pub substeps: u32,
+	// passes of the contact solver per step, 0 resolves each contact once on its own
+	pub solver_iterations: u32,
+	// spheres slower than this, m/s, for sleep_steps steps in a row fall asleep
+	pub sleep_speed: f32,
+	// 0 keeps every sphere awake
+	pub sleep_steps: u32,
+	pub spring_force: VM,
+	pub collision_response: VM,
+}
+
+fn collision_response_registers() -> HashMap<&'static str, usize> {
+	let mut registers = HashMap::new();
+	registers.insert("p", 0);
+	registers.insert("other_p", 1);
+	registers.insert("mass", 2);
+	registers.insert("restitution", 3);
+	registers.insert("other_mass", 4);
+	registers
+}
+
+fn spring_force_registers() -> HashMap<&'static str, usize> {
+	let mut registers = HashMap::new();
+	registers.insert("x", 0);
+	registers.insert("v", 1);
+	registers.insert("dampening", 2);
+	registers.insert("k", 3);
+	registers
+}
+
+fn eval_constant(expr: Expr) -> f32 {
+	let registers = HashMap::new();
+	let constant_vm = VM::compile(VM::optimize(expr), &registers);
+	let data = vec![];
+	constant_vm.run(&data) as f32
+}
+
+impl Laws {
+	pub fn new() -> Laws {
+		Laws {
+			k: 36.0,
+			g: -9.81,
+			dampening: 1.8,
+			volume_stiffness: 100.0,
+			restitution: 1.0,
+			integrator: Integrator::SymplecticEuler,
+			dt: 1.0 / 60.0,
+			substeps: 1,
+			solver_iterations: 0,
+			sleep_speed: 0.05,
+			sleep_steps: 0,
+			spring_force: VM::compile(VM::optimize(parse_expr(& mut Tokenizer::new("0 - k * x - dampening * v\n"))), &spring_force_registers()),
+			collision_response: VM::compile(VM::optimize(parse_expr(& mut Tokenizer::new("(1 + restitution) * (mass * other_p - other_mass * p) / (mass + other_mass) / mass\n"))), &collision_response_registers()),
+		}
+	}
+
+	/// Reads the laws from `path`, keeping the defaults for anything the file doesn't assign.
+	pub fn load(path: &str) -> Laws {
+		let mut laws = Laws::new();
+		match ::std::fs::File::open(path) {
+			Ok(f) => {
+				let f = ::std::io::BufReader::new(f);
+				for line in f.lines() {
+					match line {
+						Ok(line) => {
+							if !line.starts_with("//") && !line.trim().is_empty() {
+								let mut toks = Tokenizer::new(&line[..]);
+								let Line::Assign(name, expr) = parse_line(& mut toks);
+								laws.assign(&name[..], expr);
+							}
+						},
+						_ => (),
+					}
+				}
+			},
+			_ => ()
+		}
+		laws
+	}
+
+	pub fn assign(& mut self, name: &str, expr: Expr) {
+		match name {
+			"spring_force" => {
+				self.spring_force = VM::compile(VM::optimize(expr), &spring_force_registers());
+			},
+			"collision_response" => {
+				self.collision_response = VM::compile(VM::optimize(expr), &collision_response_registers());
+			},
+			"k" => self.k = eval_constant(expr),
+			"dampening" => self.dampening = eval_constant(expr),
+			"volume_stiffness" => self.volume_stiffness = eval_constant(expr),
+			"g" => self.g = eval_constant(expr),
+			"restitution" => self.restitution = eval_constant(expr),
+			"dt" => self.dt = eval_constant(expr),
+			"substeps" => self.substeps = eval_constant(expr) as u32,
+			"solver_iterations" => self.solver_iterations = eval_constant(expr) as u32,
+			"sleep_speed" => self.sleep_speed = eval_constant(expr),
+			"sleep_steps" => self.sleep_steps = eval_constant(expr) as u32,
+			"integrator" => self.integrator = match expr {
+				Expr::Variable(ref name) => Integrator::from_name(&name[..]).expect("Unknown integrator."),
+				_ => panic!("Expected an integrator name."),
+			},
+			_ => (),
+		}
+	}
+}
@@ -0,0 +1,27 @@
+extern crate nalgebra as na;
+extern crate itertools;
+
+pub mod sphere;
+pub mod shape;
+pub mod vec_tools;
+pub mod plane;
+pub mod collider;
+pub mod kinematic;
+pub mod vm;
+pub mod parser;
+pub mod softbody;
+pub mod integrator;
+pub mod collision;
+pub mod gjk;
+pub mod material;
+pub mod broadphase;
+pub mod solver;
+pub mod laws;
+pub mod world;
+pub mod timestep;
+
+pub use laws::Laws;
+pub use material::Material;
+pub use shape::{Hull, Shape};
+pub use world::World;
+pub use timestep::FixedStep;
@@ -0,0 +1,76 @@
+/// How two materials' values are merged into one for a contact.
+/// When the two materials ask for different rules, the one listed last wins.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Combine {
+	Average,
+	Min,
+	Multiply,
+	Max,
+}
+
+impl Combine {
+	pub fn apply(&self, lhs: f32, rhs: f32) -> f32 {
+		match *self {
+			Combine::Average => (lhs + rhs) / 2.0,
+			Combine::Min => lhs.min(rhs),
+			Combine::Multiply => lhs * rhs,
+			Combine::Max => lhs.max(rhs),
+		}
+	}
+}
+
+/// Surface and bulk properties of a body.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Material {
+	pub restitution: f32,
+	pub static_friction: f32,
+	pub dynamic_friction: f32,
+	// kg/m^3
+	pub density: f32,
+	pub restitution_combine: Combine,
+	pub friction_combine: Combine,
+}
+
+/// Values that apply to one contact between two materials.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ContactMaterial {
+	pub restitution: f32,
+	pub static_friction: f32,
+	pub dynamic_friction: f32,
+}
+
+impl Default for Material {
+	/// Fully elastic, with moderate friction and the density of water.
+	fn default() -> Material {
+		Material::new(1.0, 0.5, 0.3, 1000.0)
+	}
+}
+
+impl Material {
+	pub fn new(restitution: f32, static_friction: f32, dynamic_friction: f32, density: f32) -> Material {
+		Material {
+			restitution: restitution,
+			static_friction: static_friction,
+			dynamic_friction: dynamic_friction,
+			density: density,
+			restitution_combine: Combine::Average,
+			friction_combine: Combine::Average,
+		}
+	}
+
+	pub fn with_combine(mut self, restitution_combine: Combine, friction_combine: Combine) -> Material {
+		self.restitution_combine = restitution_combine;
+		self.friction_combine = friction_combine;
+		self
+	}
+
+	pub fn combine(&self, other: &Material) -> ContactMaterial {
+		let restitution_combine = ::std::cmp::max(self.restitution_combine, other.restitution_combine);
+		let friction_combine = ::std::cmp::max(self.friction_combine, other.friction_combine);
+		ContactMaterial {
+			restitution: restitution_combine.apply(self.restitution, other.restitution),
+			static_friction: friction_combine.apply(self.static_friction, other.static_friction),
+			dynamic_friction: friction_combine.apply(self.dynamic_friction, other.dynamic_friction),
+		}
+	}
+}
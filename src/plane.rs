@@ -1,29 +1,31 @@
-extern crate glutin;
-
-extern crate glium;
-
 extern crate nalgebra as na;
 
 use na::*;
+use collider::*;
+use material::*;
 use sphere::*;
 
 #[derive(Clone, Copy)]
 pub struct Plane {
 	point: Vec3<f32>,
 	normal: Vec3<f32>,
-	restitution: f32,
+	pub material: Material,
 }
 
 impl Plane {
-	pub fn new(point: Vec3<f32>, normal: Vec3<f32>, restitution: f32) -> Plane {
+	pub fn new(point: Vec3<f32>, normal: Vec3<f32>, material: Material) -> Plane {
 		Plane {
 			point: point,
 			normal: normal.normalize(),
-			restitution: restitution,
+			material: material,
 		}
 	}
 
-	pub fn check_collision(&self, sphere: &mut Sphere) -> bool {
+	pub fn normal(&self) -> Vec3<f32> {
+		self.normal
+	}
+
+	pub fn check_collision(&self, sphere: &mut Sphere, dt: f32) -> bool {
 
 		//let velocity_normal = sphere.velocity.clone().normalize();
 
@@ -35,12 +37,14 @@ impl Plane {
 		}
 
 		//dot product of previous frame - for tunneling purposes
-		let prev_dot_product = na::dot(&(sphere.position - sphere.velocity - self.point), &self.normal);
+		let prev_dot_product = na::dot(&(sphere.position - sphere.velocity * dt - self.point), &self.normal);
 		//current dot product
 		let dot_product: f32 = na::dot(&(sphere.position - self.point), &self.normal);
 
 		//vector from plane to center of sphere
 		let projection = self.normal * dot_product;
+		//how far the body reaches towards the plane, its radius for a ball
+		let extent = -na::dot(&sphere.surface_offset(-self.normal), &self.normal);
 
 		let distance: f32 = if projection.norm() < 0.0f32 {
 			projection.norm() * -1.0f32
@@ -49,31 +53,50 @@ impl Plane {
 		};
 
 		//check if sphere is currently intersecting the line
-		if velocity_direction <= 0.0f32 && distance < sphere.radius {
+		if velocity_direction <= 0.0f32 && distance < extent {
 			
 			//sphere is still on the positive side of the plane
 			if dot_product > 0.0f32 {
-				sphere.position = sphere.position + self.normal * (sphere.radius - distance);
+				sphere.position = sphere.position + self.normal * (extent - distance);
 				return true;
 			} else{
-				sphere.position = sphere.position - projection + self.normal * sphere.radius;
+				sphere.position = sphere.position - projection + self.normal * extent;
 				return true;
 			}
 
 		}
 		//check if sphere has just tunneled through plane
 		else if velocity_direction < 0.0f32 && dot_product < 0.0f32 && prev_dot_product > 0.0f32 {
-			sphere.position = sphere.position - projection + self.normal * sphere.radius;
+			sphere.position = sphere.position - projection + self.normal * extent;
 			return true;
 		}
 		false
 	}
 
-	pub fn bounce_sphere(&self, sphere: &mut Sphere){
-		sphere.velocity = self.reflect(sphere.velocity) * self.restitution; 
+	pub fn bounce_sphere(&self, sphere: &mut Sphere, resting_speed: f32){
+		bounce_off(sphere, self.normal, &self.material, na::zero(), resting_speed);
 	}
 
 	pub fn reflect(&self, vector: Vec3<f32>) -> Vec3<f32> {
 		vector - self.normal * 2.0f32 * na::dot(&vector, &self.normal) 
 	}
-}
\ No newline at end of file
+}
+
+impl StaticCollider for Plane {
+	fn material(&self) -> &Material {
+		&self.material
+	}
+
+	fn closest_point(&self, position: Vec3<f32>, _previous: Vec3<f32>) -> (Vec3<f32>, Vec3<f32>) {
+		(position - self.normal * na::dot(&(position - self.point), &self.normal), self.normal)
+	}
+
+	// planes keep their own test, which only catches spheres from behind if they just tunnelled
+	fn check_collision(&self, sphere: &mut Sphere, dt: f32) -> bool {
+		Plane::check_collision(self, sphere, dt)
+	}
+
+	fn bounce_sphere(&self, sphere: &mut Sphere, resting_speed: f32) {
+		Plane::bounce_sphere(self, sphere, resting_speed)
+	}
+}
@@ -0,0 +1,222 @@
+use std::f32::consts::PI;
+
+use glium;
+use glium::DisplayBuild;
+use glium::index;
+use glium::Surface;
+use glutin;
+use time;
+
+use na::*;
+
+use physics_proj::{FixedStep, World};
+
+#[derive(Clone, Copy)]
+pub struct Vertex {
+    position: [f32; 3],
+}
+
+implement_vertex!(Vertex, position);
+
+// set size to 1
+fn sphere_points(subdivs: u32) -> Vec<Vec3<f32>> {
+    let mut points: Vec<Vec3<f32>> = vec![];
+    let y_subdivs = subdivs;
+    let x_subdivs = subdivs * 2;
+
+    let y_angle_iter = (0..y_subdivs)
+        .map(|a| a * (90 / y_subdivs) )
+        .map(|a| a as f32 / 180.0f32 * PI );
+    let next_y_angle_iter =(1..y_subdivs + 1)
+        .map(|a| a * (90 / y_subdivs) )
+        .map(|a| a as f32 / 180.0f32 * PI );
+
+    for (y_angle, next_y_angle) in y_angle_iter.zip(next_y_angle_iter)
+    {
+        let y_dist = y_angle.cos() * 1.0;
+        let xz_dist = y_angle.sin() * 1.0;
+
+        let next_y_dist = next_y_angle.cos() * 1.0;
+        let next_xz_dist = next_y_angle.sin() * 1.0;
+
+        let xz_angle_iter = (0..x_subdivs)
+            .map(|a| a * 180 / x_subdivs )
+            .map(|a| a as f32 / 180.0 * PI );
+        let next_xz_angle_iter = (1..x_subdivs + 1)
+            .map(|a| a * 180 / x_subdivs )
+            .map(|a| a as f32 / 180.0 * PI );
+
+        for (xz_angle, next_xz_angle) in xz_angle_iter.zip(next_xz_angle_iter)
+        {
+            let tl_x_dist = xz_angle.cos() * xz_dist;
+            let tl_z_dist = xz_angle.sin() * xz_dist;
+
+            let tr_x_dist = next_xz_angle.cos() * xz_dist;
+            let tr_z_dist = next_xz_angle.sin() * xz_dist;
+
+            let bl_x_dist = xz_angle.cos() * next_xz_dist;
+            let bl_z_dist = xz_angle.sin() * next_xz_dist;
+
+            let br_x_dist = next_xz_angle.cos() * next_xz_dist;
+            let br_z_dist = next_xz_angle.sin() * next_xz_dist;
+
+            let mut make_face = |tl_x, t_y, tl_z, tr_x, tr_z, bl_x, b_y, bl_z, br_x, br_z| {
+                let top_left = Vec3::new(tl_x, t_y, tl_z);
+                let top_right = Vec3::new(tr_x, t_y, tr_z);
+                let bottom_left = Vec3::new(bl_x, b_y, bl_z);
+                let bottom_right = Vec3::new(br_x, b_y, br_z);
+
+                points.push(top_left);
+                points.push(top_right);
+                points.push(bottom_left);
+
+                points.push(top_right);
+                points.push(bottom_right);
+                points.push(bottom_left);
+
+            };
+
+            make_face(tl_x_dist, y_dist, tl_z_dist, 
+                tr_x_dist, tr_z_dist, 
+                bl_x_dist, next_y_dist, bl_z_dist, 
+                br_x_dist, br_z_dist);
+            make_face(tl_x_dist, -y_dist, tl_z_dist, 
+                tr_x_dist, tr_z_dist, 
+                bl_x_dist, -next_y_dist, bl_z_dist, 
+                br_x_dist, br_z_dist);
+
+            make_face(tl_x_dist, y_dist, -tl_z_dist, 
+                tr_x_dist, -tr_z_dist, 
+                bl_x_dist, next_y_dist, -bl_z_dist, 
+                br_x_dist, -br_z_dist);
+            make_face(tl_x_dist, -y_dist, -tl_z_dist, 
+                tr_x_dist, -tr_z_dist, 
+                bl_x_dist, -next_y_dist, -bl_z_dist, 
+                br_x_dist, -br_z_dist);
+        }
+    }
+    points
+}
+fn sphere_vertices(subdivs: u32) -> Vec<Vertex> {
+    sphere_points(subdivs).iter().map(|pt| {
+        Vertex { position: pt.as_array().clone()}
+    }).collect()
+}
+pub fn sphere_buffer<F>(display: &F, subdivs: u32) -> glium::VertexBuffer<Vertex> where F: glium::backend::Facade {
+    glium::VertexBuffer::new(display, sphere_vertices(subdivs))
+}
+
+pub fn run(world: & mut World) {
+    let display = glutin::WindowBuilder::new()
+            .with_dimensions(1024, 768)
+            .with_title(format!("Hello world"))
+            .build_glium().unwrap();
+
+    let depth_buffer = glium::render_buffer::DepthRenderBuffer::new(&display, glium::texture::DepthFormat::I24, 1024, 768);
+    let color_buffer = glium::texture::Texture2d::empty(&display, 1024, 768);
+    let mut frame_buffer = glium::framebuffer::SimpleFrameBuffer::with_depth_buffer(&display, &color_buffer, &depth_buffer);
+
+    let sphere_buf = sphere_buffer(&display, 10);
+
+    let sphere_indices = index::NoIndices(index::PrimitiveType::TrianglesList);
+
+    let program = glium::Program::from_source(&display,
+        // vertex shader
+        "   #version 110
+
+        uniform mat4 vp_matrix;
+
+        attribute vec3 position;
+        varying vec4 normal;
+
+        void main() {
+            gl_Position =  vp_matrix * vec4(position, 1.0);
+            normal = vec4(position, 1.0);
+        }
+        ",
+
+        // fragment shader
+        "   #version 110
+        uniform vec3 color;
+
+        varying vec4 normal;
+
+        void main() {
+            float mult = clamp(dot(vec4(0.0, 1.0, 0.0, 1.0), normal), 0.0, 1.0);
+            gl_FragColor = vec4(color, 1.0) * mult;
+        }
+        ",
+
+        // optional geometry shader
+        None
+    ).unwrap();
+
+    let persp = Persp3::new(640.0 / 480.0f32, 3.1415962535 / 4.0, 0.01, 200.0).to_mat();
+
+    let params = glium::DrawParameters {
+        depth_test: glium::DepthTest::IfLess,
+        depth_write: true,
+        .. ::std::default::Default::default()
+    };
+
+
+    let source_rect = glium::Rect {
+        left: 0,
+        bottom: 0,
+        width: 1024,
+        height: 768,
+    };
+    let dest_rect = glium::BlitTarget {
+        left: 0,
+        bottom: 0,
+        width: 1024,
+        height: 768,
+    };
+    let mut stepper = FixedStep::new(world.laws.dt, world.laws.substeps);
+    let mut last_time = time::precise_time_s();
+    'main_loop: loop {
+        for e in display.poll_events()
+        {
+            match e
+            {
+                glutin::Event::Closed => break 'main_loop,
+                _ => ()
+            }
+        }
+
+        let now = time::precise_time_s();
+        let alpha = stepper.advance(world, (now - last_time) as f32);
+        last_time = now;
+        let positions = stepper.interpolate(world, alpha);
+        let mut drawn = 0;
+
+        frame_buffer.clear_color(0.0, 0.0, 0.0, 0.0);
+        frame_buffer.clear_depth(1.0);
+        // bodies are drawn as their bounding balls, whatever their shape
+        for (index, s) in world.bodies.iter().enumerate() {
+            let touching = world.contacts().iter().any(|&(l, r)| l == index || r == index);
+            let color = if touching { Vec3::new(0.0f32, 1.0, 0.0) } else { Vec3::new(1.0f32, 0.0, 0.0) };
+            let uniforms = uniform! {
+                vp_matrix: *(persp * s.get_homogeneous_at(positions[drawn])).as_array(),
+                color: *color.as_array(),
+            };
+
+            frame_buffer.draw(&sphere_buf, &sphere_indices, &program, &uniforms, &params).unwrap();
+            drawn += 1;
+        }
+
+        for soft_body in world.soft_bodies.iter() {
+            for s in soft_body.get_points().iter() {
+                let uniforms = uniform! {
+                    vp_matrix: *(persp * s.get_homogeneous_at(positions[drawn])).as_array(),
+                    color: *Vec3::new(1.0, 1.0, 1.0).as_array(),
+                };
+
+                frame_buffer.draw(&sphere_buf, &sphere_indices, &program, &uniforms, &params).unwrap();
+                drawn += 1;
+            }
+        }
+
+        frame_buffer.blit_color(&source_rect, & mut display.draw(), &dest_rect, glium::uniforms::MagnifySamplerFilter::Nearest);
+    }
+}
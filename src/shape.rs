@@ -0,0 +1,142 @@
+extern crate nalgebra as na;
+
+use na::*;
+use sphere::*;
+
+/// Convex outline of a body, in body space around its centre of mass.
+/// A body's `radius` bounds its shape, so broadphase and swept tests can keep treating it as a ball.
+#[derive(Clone, Debug)]
+pub enum Shape {
+	// radius
+	Ball(f32),
+	// half extents along the body axes
+	Cuboid(Vec3<f32>),
+	// half length of the core segment along the body y axis, radius around it
+	Capsule(f32, f32),
+	ConvexHull(Hull),
+}
+
+/// Vertices of a convex hull, centred on the origin. Always has at least one.
+#[derive(Clone, Debug)]
+pub struct Hull {
+	vertices: Vec<Vec3<f32>>,
+}
+
+impl Hull {
+	/// None if `vertices` is empty.
+	pub fn new(vertices: Vec<Vec3<f32>>) -> Option<Hull> {
+		if vertices.is_empty() {
+			None
+		} else {
+			Some(Hull { vertices: vertices })
+		}
+	}
+
+	pub fn vertices(&self) -> &[Vec3<f32>] {
+		&self.vertices
+	}
+}
+
+// share of the bounding radius within which extreme points count as one flat contact
+const CONTACT_TOLERANCE: f32 = 0.02;
+
+fn sign(value: f32) -> f32 {
+	if value < 0.0 { -1.0 } else { 1.0 }
+}
+
+fn box_inertia(half_extents: Vec3<f32>, mass: f32) -> Mat3<f32> {
+	let (x, y, z) = (half_extents.x * half_extents.x, half_extents.y * half_extents.y, half_extents.z * half_extents.z);
+	Mat3::new(mass / 3.0 * (y + z), 0.0, 0.0,
+	          0.0, mass / 3.0 * (x + z), 0.0,
+	          0.0, 0.0, mass / 3.0 * (x + y))
+}
+
+impl Shape {
+	/// Point of the shape furthest along `direction`.
+	pub fn support(&self, direction: Vec3<f32>) -> Vec3<f32> {
+		match *self {
+			Shape::Ball(radius) => if direction.sqnorm() > 0.0 { direction.normalize() * radius } else { na::zero() },
+			Shape::Cuboid(half_extents) => Vec3::new(sign(direction.x) * half_extents.x,
+				sign(direction.y) * half_extents.y, sign(direction.z) * half_extents.z),
+			Shape::Capsule(half_length, capsule_radius) => {
+				let end = Vec3::new(0.0, sign(direction.y) * half_length, 0.0);
+				if direction.sqnorm() > 0.0 { end + direction.normalize() * capsule_radius } else { end }
+			},
+			Shape::ConvexHull(ref hull) => {
+				let mut best = hull.vertices[0];
+				for vertex in hull.vertices.iter() {
+					if na::dot(vertex, &direction) > na::dot(&best, &direction) {
+						best = *vertex;
+					}
+				}
+				best
+			},
+		}
+	}
+
+	/// Middle of the shape's extreme points along `direction`: the centre of a face or edge lying flat
+	/// against something in that direction, or the single furthest point otherwise.
+	pub fn contact_support(&self, direction: Vec3<f32>) -> Vec3<f32> {
+		let candidates = match *self {
+			Shape::Ball(_) => return self.support(direction),
+			Shape::Cuboid(h) => vec![
+				Vec3::new(-h.x, -h.y, -h.z), Vec3::new(h.x, -h.y, -h.z), Vec3::new(-h.x, h.y, -h.z), Vec3::new(h.x, h.y, -h.z),
+				Vec3::new(-h.x, -h.y, h.z), Vec3::new(h.x, -h.y, h.z), Vec3::new(-h.x, h.y, h.z), Vec3::new(h.x, h.y, h.z),
+			],
+			Shape::Capsule(half_length, capsule_radius) => {
+				let rim = if direction.sqnorm() > 0.0 { direction.normalize() * capsule_radius } else { na::zero() };
+				vec![Vec3::new(0.0, half_length, 0.0) + rim, Vec3::new(0.0, -half_length, 0.0) + rim]
+			},
+			Shape::ConvexHull(ref hull) => hull.vertices.clone(),
+		};
+		let furthest = na::dot(&self.support(direction), &direction);
+		let tolerance = CONTACT_TOLERANCE * self.bounding_radius() * direction.norm();
+
+		let mut sum: Vec3<f32> = na::zero();
+		let mut count = 0;
+		for candidate in candidates.iter() {
+			if na::dot(candidate, &direction) >= furthest - tolerance {
+				sum = sum + *candidate;
+				count += 1;
+			}
+		}
+		if count == 0 {
+			return self.support(direction);
+		}
+		sum / (count as f32)
+	}
+
+	/// Radius of the smallest ball around the origin holding the shape.
+	pub fn bounding_radius(&self) -> f32 {
+		match *self {
+			Shape::Ball(radius) => radius,
+			Shape::Cuboid(half_extents) => half_extents.norm(),
+			Shape::Capsule(half_length, radius) => half_length + radius,
+			Shape::ConvexHull(ref hull) => hull.vertices.iter().fold(0.0, |r: f32, v| r.max(v.norm())),
+		}
+	}
+
+	/// Body space inertia tensor of the shape filled uniformly with `mass`.
+	/// Capsules are taken as cylinders of their full length and hulls as their bounding box.
+	pub fn inertia(&self, mass: f32) -> Mat3<f32> {
+		match *self {
+			Shape::Ball(radius) => solid_sphere_inertia(radius, mass),
+			Shape::Cuboid(half_extents) => box_inertia(half_extents, mass),
+			Shape::Capsule(half_length, capsule_radius) => {
+				let length = 2.0 * (half_length + capsule_radius);
+				let across = mass * (3.0 * capsule_radius * capsule_radius + length * length) / 12.0;
+				let along = 0.5 * mass * capsule_radius * capsule_radius;
+				Mat3::new(across, 0.0, 0.0,
+				          0.0, along, 0.0,
+				          0.0, 0.0, across)
+			},
+			Shape::ConvexHull(ref hull) => {
+				let mut half_extents: Vec3<f32> = na::zero();
+				for v in hull.vertices.iter() {
+					half_extents = Vec3::new(half_extents.x.max(v.x.abs()), half_extents.y.max(v.y.abs()), half_extents.z.max(v.z.abs()));
+				}
+				box_inertia(half_extents, mass)
+			},
+		}
+	}
+}
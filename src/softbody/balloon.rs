@@ -0,0 +1,145 @@
+extern crate nalgebra as na;
+
+use std::collections::HashMap;
+
+use na::*;
+use sphere::*;
+use super::{enclosed_volume, ConnectionData, SoftBody, SpringKind, SpringParams};
+
+/// Generates gas filled soft bodies: a shell of points on a subdivided icosahedron, joined by springs
+/// along its edges and pushed outward by the pressure of the gas inside.
+#[derive(Clone, Debug)]
+pub struct BalloonBuilder {
+	subdivisions: u32,
+	point_mass: f32,
+	point_radius: Option<f32>,
+	springs: SpringParams,
+	pressure: f32,
+}
+
+// index of the point halfway along an edge, creating it the first time the edge is seen
+fn midpoint(lhs: usize, rhs: usize, vertices: & mut Vec<Vec3<f32>>, cache: & mut HashMap<(usize, usize), usize>) -> usize {
+	let key = if lhs < rhs { (lhs, rhs) } else { (rhs, lhs) };
+	match cache.get(&key) {
+		Some(&index) => return index,
+		None => (),
+	}
+	let middle = ((vertices[lhs] + vertices[rhs]) * 0.5).normalize();
+	vertices.push(middle);
+	cache.insert(key, vertices.len() - 1);
+	vertices.len() - 1
+}
+
+// unit icosphere, triangles wound counter-clockwise seen from outside
+fn icosphere(subdivisions: u32) -> (Vec<Vec3<f32>>, Vec<[usize; 3]>) {
+	let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+	let mut vertices: Vec<Vec3<f32>> = vec![
+		Vec3::new(-1.0, t, 0.0), Vec3::new(1.0, t, 0.0), Vec3::new(-1.0, -t, 0.0), Vec3::new(1.0, -t, 0.0),
+		Vec3::new(0.0, -1.0, t), Vec3::new(0.0, 1.0, t), Vec3::new(0.0, -1.0, -t), Vec3::new(0.0, 1.0, -t),
+		Vec3::new(t, 0.0, -1.0), Vec3::new(t, 0.0, 1.0), Vec3::new(-t, 0.0, -1.0), Vec3::new(-t, 0.0, 1.0),
+	].into_iter().map(|v| v.normalize()).collect();
+	let mut faces = vec![
+		[0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+		[1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+		[3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+		[4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+	];
+
+	for _ in 0..subdivisions {
+		let mut cache = HashMap::new();
+		let mut next_faces = Vec::with_capacity(faces.len() * 4);
+		for face in faces.iter() {
+			let a = midpoint(face[0], face[1], & mut vertices, & mut cache);
+			let b = midpoint(face[1], face[2], & mut vertices, & mut cache);
+			let c = midpoint(face[2], face[0], & mut vertices, & mut cache);
+			next_faces.push([face[0], a, c]);
+			next_faces.push([face[1], b, a]);
+			next_faces.push([face[2], c, b]);
+			next_faces.push([a, b, c]);
+		}
+		faces = next_faces;
+	}
+	(vertices, faces)
+}
+
+impl BalloonBuilder {
+	pub fn new() -> BalloonBuilder {
+		BalloonBuilder {
+			subdivisions: 2,
+			point_mass: 0.05,
+			point_radius: None,
+			springs: SpringParams::global(),
+			pressure: 10.0,
+		}
+	}
+
+	/// Times each icosahedron face is split in four; 2 gives 162 points.
+	pub fn subdivisions(mut self, subdivisions: u32) -> BalloonBuilder {
+		self.subdivisions = subdivisions;
+		self
+	}
+
+	pub fn point_mass(mut self, mass: f32) -> BalloonBuilder {
+		self.point_mass = mass;
+		self
+	}
+
+	/// Collision radius of each point. Defaults to half the shortest edge.
+	pub fn point_radius(mut self, radius: f32) -> BalloonBuilder {
+		self.point_radius = Some(radius);
+		self
+	}
+
+	pub fn springs(mut self, params: SpringParams) -> BalloonBuilder {
+		self.springs = params;
+		self
+	}
+
+	/// Gas pressure, in Pa, when the balloon has its initial volume.
+	pub fn pressure(mut self, pressure: f32) -> BalloonBuilder {
+		self.pressure = pressure;
+		self
+	}
+
+	pub fn build(&self, position: Vec3<f32>, radius: f32) -> SoftBody {
+		let (vertices, faces) = icosphere(self.subdivisions);
+
+		let mut edges = vec![];
+		for face in faces.iter() {
+			for corner in 0..3 {
+				let (lhs, rhs) = (face[corner], face[(corner + 1) % 3]);
+				// every edge is shared by two faces, keep the one that walks it upward
+				if lhs < rhs {
+					edges.push((lhs, rhs));
+				}
+			}
+		}
+
+		let shortest = edges.iter()
+			.map(|&(lhs, rhs)| (vertices[rhs] - vertices[lhs]).norm() * radius)
+			.fold(::std::f32::MAX, |a, b| a.min(b));
+		let point_radius = self.point_radius.unwrap_or(shortest / 2.0);
+
+		let points: Vec<_> = vertices.iter().map(|v| {
+			let mut new_sphere = Sphere::new(point_radius, self.point_mass);
+			new_sphere.position = position + *v * radius;
+			new_sphere
+		}).collect();
+
+		let connections = edges.into_iter().map(|(lhs, rhs)| ConnectionData {
+			lhs: lhs,
+			rhs: rhs,
+			starting_distance: (points[rhs].position - points[lhs].position).norm(),
+			kind: SpringKind::Structural,
+			params: self.springs,
+		}).collect();
+
+		let positions: Vec<_> = points.iter().map(|p| p.position).collect();
+		let volume = enclosed_volume(&faces, &positions);
+
+		let mut soft_body = SoftBody::from_parts(points, connections);
+		soft_body.gas = Some(self.pressure * volume);
+		soft_body.faces = faces;
+		soft_body
+	}
+}
@@ -0,0 +1,271 @@
+extern crate nalgebra as na;
+
+use std::collections::HashMap;
+
+use na::*;
+use sphere::*;
+use super::{ConnectionData, SoftBody, SpringKind, SpringParams};
+use super::volume::{signed_volume, Tetrahedron, VolumeConstraint};
+
+/// Overall form of the particle lattice.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LatticeShape {
+	Cube,
+	// lattice points inside the inscribed sphere
+	Ball,
+	// full width and depth, this many layers tall
+	Slab(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Face {
+	Top,
+	Bottom,
+	Left,
+	Right,
+	Front,
+	Back,
+}
+
+/// Which points of a generated soft body are held fixed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Anchors {
+	None,
+	// every point on the outermost layer facing this way
+	Face(Face),
+	// point indices in generation order
+	Indices(Vec<usize>),
+}
+
+/// Configures and generates lattice soft bodies.
+/// The defaults match `SoftBody::new`: a 7x7x7 cube of 0.2kg points hanging from its top face,
+/// with only structural springs.
+#[derive(Clone, Debug)]
+pub struct SoftBodyBuilder {
+	resolution: u32,
+	point_mass: f32,
+	point_radius: Option<f32>,
+	shape: LatticeShape,
+	anchors: Anchors,
+	structural: SpringParams,
+	shear: Option<SpringParams>,
+	bend: Option<SpringParams>,
+	volume_constraint: VolumeConstraint,
+}
+
+// offsets to the diagonal neighbours, only those pointing "forward" so each pair is joined once
+fn shear_offsets() -> Vec<(i32, i32, i32)> {
+	let mut offsets = vec![];
+	for dx in -1..2 {
+		for dy in -1..2 {
+			for dz in -1..2 {
+				let nonzero = [dx, dy, dz].iter().filter(|d| **d != 0).count();
+				if nonzero >= 2 && (dx, dy, dz) > (0, 0, 0) {
+					offsets.push((dx, dy, dz));
+				}
+			}
+		}
+	}
+	offsets
+}
+
+// six tetrahedra per cell, all sharing the cell's main diagonal, skipping any with a missing corner
+// and ordered so each has a positive rest volume
+fn tetrahedra(coords: &[(i32, i32, i32)], indices: &HashMap<(i32, i32, i32), usize>, positions: &[Vec3<f32>]) -> Vec<Tetrahedron> {
+	let axes = [(1, 0, 0), (0, 1, 0), (0, 0, 1)];
+	let orders = [(0, 1, 2), (0, 2, 1), (1, 0, 2), (1, 2, 0), (2, 0, 1), (2, 1, 0)];
+	let mut tetrahedra = vec![];
+	for &(x, y, z) in coords.iter() {
+		let corner = |(dx, dy, dz): (i32, i32, i32)| indices.get(&(x + dx, y + dy, z + dz)).cloned();
+		for &(first, second, _) in orders.iter() {
+			let (a, b) = (axes[first], axes[second]);
+			let path = [(0, 0, 0), a, (a.0 + b.0, a.1 + b.1, a.2 + b.2), (1, 1, 1)];
+			let mut corners = [0; 4];
+			let mut complete = true;
+			for (slot, offset) in corners.iter_mut().zip(path.iter()) {
+				match corner(*offset) {
+					Some(index) => *slot = index,
+					None => complete = false,
+				}
+			}
+			if !complete {
+				continue;
+			}
+			if signed_volume(&corners, positions) < 0.0 {
+				corners.swap(2, 3);
+			}
+			tetrahedra.push(Tetrahedron {
+				corners: corners,
+				rest_volume: signed_volume(&corners, positions),
+			});
+		}
+	}
+	tetrahedra
+}
+
+impl SoftBodyBuilder {
+	pub fn new() -> SoftBodyBuilder {
+		SoftBodyBuilder {
+			resolution: 3,
+			point_mass: 0.2,
+			point_radius: None,
+			shape: LatticeShape::Cube,
+			anchors: Anchors::Face(Face::Top),
+			structural: SpringParams::global(),
+			shear: None,
+			bend: None,
+			volume_constraint: VolumeConstraint::None,
+		}
+	}
+
+	/// Number of lattice spacings from the centre to the edge along each axis.
+	pub fn resolution(mut self, resolution: u32) -> SoftBodyBuilder {
+		self.resolution = if resolution == 0 { 1 } else { resolution };
+		self
+	}
+
+	pub fn point_mass(mut self, mass: f32) -> SoftBodyBuilder {
+		self.point_mass = mass;
+		self
+	}
+
+	/// Collision radius of each point. Defaults to half the lattice spacing so neighbours just touch.
+	pub fn point_radius(mut self, radius: f32) -> SoftBodyBuilder {
+		self.point_radius = Some(radius);
+		self
+	}
+
+	pub fn shape(mut self, shape: LatticeShape) -> SoftBodyBuilder {
+		self.shape = shape;
+		self
+	}
+
+	pub fn anchors(mut self, anchors: Anchors) -> SoftBodyBuilder {
+		self.anchors = anchors;
+		self
+	}
+
+	pub fn structural_springs(mut self, params: SpringParams) -> SoftBodyBuilder {
+		self.structural = params;
+		self
+	}
+
+	/// Adds springs across the diagonals of each lattice cell.
+	pub fn shear_springs(mut self, params: SpringParams) -> SoftBodyBuilder {
+		self.shear = Some(params);
+		self
+	}
+
+	/// Adds springs that skip one point along each axis.
+	pub fn bend_springs(mut self, params: SpringParams) -> SoftBodyBuilder {
+		self.bend = Some(params);
+		self
+	}
+
+	/// Splits the lattice cells into tetrahedra, six per cell, whose volume is preserved.
+	pub fn volume_constraint(mut self, constraint: VolumeConstraint) -> SoftBodyBuilder {
+		self.volume_constraint = constraint;
+		self
+	}
+
+	fn contains(&self, x: i32, y: i32, z: i32) -> bool {
+		let half_dim = self.resolution as i32;
+		match self.shape {
+			LatticeShape::Cube => true,
+			LatticeShape::Ball => x * x + y * y + z * z <= half_dim * half_dim,
+			LatticeShape::Slab(layers) => {
+				let bottom = -(layers as i32 - 1) / 2;
+				y >= bottom && y < bottom + layers as i32
+			},
+		}
+	}
+
+	/// Generates the lattice centred on `position`, extending `radius` from the centre along each axis.
+	pub fn build(&self, position: Vec3<f32>, radius: f32) -> SoftBody {
+		let half_dim = self.resolution as i32;
+		let dist = radius / half_dim as f32;
+		let point_radius = self.point_radius.unwrap_or(dist / 2.0);
+
+		let mut coords = vec![];
+		for z in (-half_dim .. half_dim + 1) {
+			for x in (-half_dim .. half_dim + 1) {
+				for y in (-half_dim .. half_dim + 1) {
+					if self.contains(x, y, z) {
+						coords.push((x, y, z));
+					}
+				}
+			}
+		}
+		let indices: HashMap<(i32, i32, i32), usize> = coords.iter().enumerate().map(|(i, c)| (*c, i)).collect();
+
+		let mut offsets = vec![((1, 0, 0), SpringKind::Structural), ((0, 1, 0), SpringKind::Structural), ((0, 0, 1), SpringKind::Structural)];
+		if self.shear.is_some() {
+			offsets.extend(shear_offsets().into_iter().map(|o| (o, SpringKind::Shear)));
+		}
+		if self.bend.is_some() {
+			offsets.extend(vec![((2, 0, 0), SpringKind::Bend), ((0, 2, 0), SpringKind::Bend), ((0, 0, 2), SpringKind::Bend)]);
+		}
+
+		let mut points = Vec::with_capacity(coords.len());
+		let mut connections = Vec::new();
+		for (sphere_index, &(x, y, z)) in coords.iter().enumerate() {
+			let mut new_sphere = Sphere::new(point_radius, self.point_mass);
+			new_sphere.position = position;
+			new_sphere.position.x += x as f32 * dist;
+			new_sphere.position.y += y as f32 * dist;
+			new_sphere.position.z += z as f32 * dist;
+			points.push(new_sphere);
+
+			for &((dx, dy, dz), kind) in offsets.iter() {
+				match indices.get(&(x + dx, y + dy, z + dz)) {
+					Some(&neighbour_index) => connections.push(ConnectionData {
+						lhs: sphere_index,
+						rhs: neighbour_index,
+						starting_distance: dist * (((dx * dx + dy * dy + dz * dz) as f32).sqrt()),
+						kind: kind,
+						params: match kind {
+							SpringKind::Structural => self.structural,
+							SpringKind::Shear => self.shear.unwrap_or(SpringParams::global()),
+							SpringKind::Bend => self.bend.unwrap_or(SpringParams::global()),
+						},
+					}),
+					None => (),
+				}
+			}
+		}
+
+		match self.anchors {
+			Anchors::None => (),
+			Anchors::Face(face) => {
+				let along = |&(x, y, z): &(i32, i32, i32)| match face {
+					Face::Top => y,
+					Face::Bottom => -y,
+					Face::Right => x,
+					Face::Left => -x,
+					Face::Back => z,
+					Face::Front => -z,
+				};
+				let outermost = coords.iter().map(|c| along(c)).max();
+				for (point, coord) in points.iter_mut().zip(coords.iter()) {
+					point.fixed = Some(along(coord)) == outermost;
+				}
+			},
+			Anchors::Indices(ref anchored) => {
+				for &index in anchored.iter() {
+					match points.get_mut(index) {
+						Some(point) => point.fixed = true,
+						None => (),
+					}
+				}
+			},
+		}
+
+		let mut soft_body = SoftBody::from_parts(points, connections);
+		if self.volume_constraint != VolumeConstraint::None {
+			let positions: Vec<_> = soft_body.points.iter().map(|p| p.position).collect();
+			soft_body.tetrahedra = tetrahedra(&coords, &indices, &positions);
+			soft_body.volume_constraint = self.volume_constraint;
+		}
+		soft_body
+	}
+}
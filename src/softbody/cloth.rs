@@ -0,0 +1,220 @@
+extern crate nalgebra as na;
+
+use na::*;
+use sphere::*;
+use super::{ConnectionData, SoftBody, SpringKind, SpringParams};
+
+// joins `lhs` and `rhs` at their current distance
+fn connect(points: &[Sphere], lhs: usize, rhs: usize, kind: SpringKind, params: SpringParams) -> ConnectionData {
+	ConnectionData {
+		lhs: lhs,
+		rhs: rhs,
+		starting_distance: (points[rhs].position - points[lhs].position).norm(),
+		kind: kind,
+		params: params,
+	}
+}
+
+/// Generates 2D cloth: a grid of points spanning two directions, with structural springs between
+/// neighbours and optional shear and bend springs.
+#[derive(Clone, Debug)]
+pub struct ClothBuilder {
+	columns: u32,
+	rows: u32,
+	point_mass: f32,
+	point_radius: Option<f32>,
+	structural: SpringParams,
+	shear: Option<SpringParams>,
+	bend: Option<SpringParams>,
+	pinned: Vec<(u32, u32)>,
+}
+
+impl ClothBuilder {
+	/// A `columns` by `rows` grid with its two top corners pinned.
+	pub fn new(columns: u32, rows: u32) -> ClothBuilder {
+		let columns = if columns < 2 { 2 } else { columns };
+		let rows = if rows < 2 { 2 } else { rows };
+		ClothBuilder {
+			columns: columns,
+			rows: rows,
+			point_mass: 0.05,
+			point_radius: None,
+			structural: SpringParams::global(),
+			shear: None,
+			bend: None,
+			pinned: vec![(0, 0), (columns - 1, 0)],
+		}
+	}
+
+	pub fn point_mass(mut self, mass: f32) -> ClothBuilder {
+		self.point_mass = mass;
+		self
+	}
+
+	/// Collision radius of each point. Defaults to half the grid spacing.
+	pub fn point_radius(mut self, radius: f32) -> ClothBuilder {
+		self.point_radius = Some(radius);
+		self
+	}
+
+	pub fn structural_springs(mut self, params: SpringParams) -> ClothBuilder {
+		self.structural = params;
+		self
+	}
+
+	/// Adds springs across the diagonals of each grid cell.
+	pub fn shear_springs(mut self, params: SpringParams) -> ClothBuilder {
+		self.shear = Some(params);
+		self
+	}
+
+	/// Adds springs that skip one point along rows and columns.
+	pub fn bend_springs(mut self, params: SpringParams) -> ClothBuilder {
+		self.bend = Some(params);
+		self
+	}
+
+	/// Grid cells, as (column, row), held fixed. Row 0 is the first row, at the origin.
+	pub fn pinned(mut self, pinned: Vec<(u32, u32)>) -> ClothBuilder {
+		self.pinned = pinned;
+		self
+	}
+
+	/// Lays the grid out from `origin`, columns along `across` and rows along `down`.
+	/// The length of each direction is the size of the whole cloth along it.
+	pub fn build(&self, origin: Vec3<f32>, across: Vec3<f32>, down: Vec3<f32>) -> SoftBody {
+		let column_step = across / (self.columns - 1) as f32;
+		let row_step = down / (self.rows - 1) as f32;
+		let spacing = column_step.norm().min(row_step.norm());
+		let point_radius = self.point_radius.unwrap_or(spacing / 2.0);
+		let index = |column: u32, row: u32| (row * self.columns + column) as usize;
+
+		let mut points = Vec::with_capacity((self.columns * self.rows) as usize);
+		for row in 0..self.rows {
+			for column in 0..self.columns {
+				let mut new_sphere = Sphere::new(point_radius, self.point_mass);
+				new_sphere.position = origin + column_step * column as f32 + row_step * row as f32;
+				new_sphere.fixed = self.pinned.contains(&(column, row));
+				points.push(new_sphere);
+			}
+		}
+
+		let mut connections = vec![];
+		for row in 0..self.rows {
+			for column in 0..self.columns {
+				let here = index(column, row);
+				if column + 1 < self.columns {
+					connections.push(connect(&points, here, index(column + 1, row), SpringKind::Structural, self.structural));
+				}
+				if row + 1 < self.rows {
+					connections.push(connect(&points, here, index(column, row + 1), SpringKind::Structural, self.structural));
+				}
+				match self.shear {
+					Some(params) if column + 1 < self.columns && row + 1 < self.rows => {
+						connections.push(connect(&points, here, index(column + 1, row + 1), SpringKind::Shear, params));
+						connections.push(connect(&points, index(column + 1, row), index(column, row + 1), SpringKind::Shear, params));
+					},
+					_ => (),
+				}
+				match self.bend {
+					Some(params) => {
+						if column + 2 < self.columns {
+							connections.push(connect(&points, here, index(column + 2, row), SpringKind::Bend, params));
+						}
+						if row + 2 < self.rows {
+							connections.push(connect(&points, here, index(column, row + 2), SpringKind::Bend, params));
+						}
+					},
+					None => (),
+				}
+			}
+		}
+
+		SoftBody::from_parts(points, connections)
+	}
+}
+
+/// Generates 1D ropes and chains: points in a line joined by springs, optionally stiffened against
+/// bending.
+#[derive(Clone, Debug)]
+pub struct RopeBuilder {
+	segments: u32,
+	point_mass: f32,
+	point_radius: Option<f32>,
+	structural: SpringParams,
+	bend: Option<SpringParams>,
+	pin_start: bool,
+	pin_end: bool,
+}
+
+impl RopeBuilder {
+	/// A rope of `segments` springs hanging from its start.
+	pub fn new(segments: u32) -> RopeBuilder {
+		RopeBuilder {
+			segments: if segments == 0 { 1 } else { segments },
+			point_mass: 0.05,
+			point_radius: None,
+			structural: SpringParams::global(),
+			bend: None,
+			pin_start: true,
+			pin_end: false,
+		}
+	}
+
+	pub fn point_mass(mut self, mass: f32) -> RopeBuilder {
+		self.point_mass = mass;
+		self
+	}
+
+	/// Collision radius of each point. Defaults to half the segment length, so links just touch like a chain.
+	pub fn point_radius(mut self, radius: f32) -> RopeBuilder {
+		self.point_radius = Some(radius);
+		self
+	}
+
+	pub fn structural_springs(mut self, params: SpringParams) -> RopeBuilder {
+		self.structural = params;
+		self
+	}
+
+	/// Adds springs that skip one point, making the rope resist bending.
+	pub fn bend_springs(mut self, params: SpringParams) -> RopeBuilder {
+		self.bend = Some(params);
+		self
+	}
+
+	pub fn pin_ends(mut self, start: bool, end: bool) -> RopeBuilder {
+		self.pin_start = start;
+		self.pin_end = end;
+		self
+	}
+
+	pub fn build(&self, start: Vec3<f32>, end: Vec3<f32>) -> SoftBody {
+		let step = (end - start) / self.segments as f32;
+		let point_radius = self.point_radius.unwrap_or(step.norm() / 2.0);
+
+		let mut points = Vec::with_capacity(self.segments as usize + 1);
+		for i in 0..self.segments + 1 {
+			let mut new_sphere = Sphere::new(point_radius, self.point_mass);
+			new_sphere.position = start + step * i as f32;
+			new_sphere.fixed = (i == 0 && self.pin_start) || (i == self.segments && self.pin_end);
+			points.push(new_sphere);
+		}
+
+		let count = points.len();
+		let mut connections = vec![];
+		for i in 0..count {
+			if i + 1 < count {
+				connections.push(connect(&points, i, i + 1, SpringKind::Structural, self.structural));
+			}
+			match self.bend {
+				Some(params) if i + 2 < count => {
+					connections.push(connect(&points, i, i + 2, SpringKind::Bend, params));
+				},
+				_ => (),
+			}
+		}
+
+		SoftBody::from_parts(points, connections)
+	}
+}
@@ -0,0 +1,385 @@
+extern crate nalgebra as na;
+
+use std::collections::HashSet;
+
+use vec_tools::*;
+use vm::*;
+
+use na::*;
+use laws::*;
+use sphere::*;
+
+pub use self::balloon::*;
+pub use self::builder::*;
+pub use self::cloth::*;
+pub use self::pbd::Solver;
+pub use self::volume::VolumeConstraint;
+
+mod balloon;
+mod builder;
+mod cloth;
+mod pbd;
+mod volume;
+
+/// Role a spring plays in the lattice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpringKind {
+	// joins axis-aligned neighbours
+	Structural,
+	// joins diagonal neighbours, resisting shear
+	Shear,
+	// joins points two apart along an axis, resisting bending
+	Bend,
+}
+
+/// Permanent deformation of a spring: past `yield_strain` its rest length creeps toward its current
+/// length, absorbing `rate` of the excess strain per second.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plasticity {
+	pub yield_strain: f32,
+	pub rate: f32,
+}
+
+/// Material of a single spring. `None` stiffness or damping falls back to the global `k` and `dampening`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpringParams {
+	pub k: Option<f32>,
+	pub dampening: Option<f32>,
+	// largest allowed |length - rest length| / rest length, enforced after each step
+	pub max_strain: Option<f32>,
+	// (length - rest length) / rest length past which the spring tears
+	pub break_strain: Option<f32>,
+	pub plasticity: Option<Plasticity>,
+}
+
+impl SpringParams {
+	pub fn new(k: f32, dampening: f32) -> SpringParams {
+		SpringParams {
+			k: Some(k),
+			dampening: Some(dampening),
+			max_strain: None,
+			break_strain: None,
+			plasticity: None,
+		}
+	}
+
+	/// Uses the global `k` and `dampening` from the laws.
+	pub fn global() -> SpringParams {
+		SpringParams {
+			k: None,
+			dampening: None,
+			max_strain: None,
+			break_strain: None,
+			plasticity: None,
+		}
+	}
+
+	pub fn with_max_strain(mut self, max_strain: f32) -> SpringParams {
+		self.max_strain = Some(max_strain);
+		self
+	}
+
+	pub fn with_plasticity(mut self, yield_strain: f32, rate: f32) -> SpringParams {
+		self.plasticity = Some(Plasticity {
+			yield_strain: yield_strain,
+			rate: rate,
+		});
+		self
+	}
+
+	/// Makes the spring breakable. Strain limiting runs after tearing, so this should exceed any `max_strain`.
+	pub fn with_break_strain(mut self, break_strain: f32) -> SpringParams {
+		self.break_strain = Some(break_strain);
+		self
+	}
+
+	fn resolve(&self, k: f32, dampening: f32) -> (f32, f32) {
+		(self.k.unwrap_or(k), self.dampening.unwrap_or(dampening))
+	}
+}
+
+/// A spring between two points of a soft body.
+#[derive(Debug)]
+pub struct ConnectionData {
+	lhs: usize,
+	rhs: usize,
+	// rest length
+	pub starting_distance: f32,
+	pub kind: SpringKind,
+	pub params: SpringParams,
+}
+
+impl ConnectionData {
+	pub fn lhs(&self) -> usize {
+		self.lhs
+	}
+
+	pub fn rhs(&self) -> usize {
+		self.rhs
+	}
+}
+
+/// Emitted when a connection strains past its `break_strain` and is removed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TearEvent {
+	pub lhs: usize,
+	pub rhs: usize,
+	pub strain: f32,
+}
+
+pub struct SoftBody {
+	points: Vec<Sphere>,
+	connections: Vec<ConnectionData>,
+	// point pairs joined by a connection, smaller index first
+	linked: HashSet<(usize, usize)>,
+	// whether points not joined by a connection collide with each other
+	pub self_collision: bool,
+	// tears since the host last drained them
+	tear_events: Vec<TearEvent>,
+	// outward facing surface triangles of gas filled bodies
+	faces: Vec<[usize; 3]>,
+	// pressure times volume of the enclosed gas, constant as the surface deforms
+	gas: Option<f32>,
+	pub solver: Solver,
+	// cells of lattice bodies, for volume preservation
+	tetrahedra: Vec<volume::Tetrahedron>,
+	pub volume_constraint: VolumeConstraint,
+}
+
+fn link_key(lhs: usize, rhs: usize) -> (usize, usize) {
+	if lhs < rhs { (lhs, rhs) } else { (rhs, lhs) }
+}
+
+impl SoftBody {
+	/// A 7x7x7 cube of points hanging from its top face, see `SoftBodyBuilder` for other lattices.
+	pub fn new(position: Vec3<f32>, radius: f32) -> SoftBody {
+		SoftBodyBuilder::new().build(position, radius)
+	}
+
+	fn from_parts(points: Vec<Sphere>, connections: Vec<ConnectionData>) -> SoftBody {
+		let linked = connections.iter().map(|c| link_key(c.lhs, c.rhs)).collect();
+		SoftBody {
+			points: points,
+			connections: connections,
+			linked: linked,
+			self_collision: false,
+			tear_events: vec![],
+			faces: vec![],
+			gas: None,
+			solver: Solver::Force,
+			tetrahedra: vec![],
+			volume_constraint: VolumeConstraint::None,
+		}
+	}
+
+	/// Volume enclosed by the surface triangles, zero for bodies without a surface.
+	pub fn volume(&self) -> f32 {
+		let positions: Vec<_> = self.points.iter().map(|p| p.position).collect();
+		enclosed_volume(&self.faces, &positions)
+	}
+
+	/// Advances every point by `dt`, with spring forces re-evaluated whenever the integrator asks for them.
+	/// Springs without their own params use `laws.k` and `laws.dampening`, and volume constraints `laws.volume_stiffness`.
+	/// With `Solver::Position` the integrator and spring force law are not used.
+	pub fn update(& mut self, dt: f32, laws: &Laws) {
+		let mut positions: Vec<_> = self.points.iter().map(|p| p.position).collect();
+		let mut velocities: Vec<_> = self.points.iter().map(|p| p.velocity).collect();
+		match self.solver {
+			Solver::Force => self.integrate_forces(& mut positions, & mut velocities, dt, laws),
+			Solver::Position { iterations } => pbd::solve_positions(self, & mut positions, & mut velocities, dt, iterations, laws),
+		}
+		for (sphere, (position, velocity)) in self.points.iter_mut().zip(positions.into_iter().zip(velocities.into_iter())) {
+			sphere.force = na::zero();
+			if sphere.fixed {
+				sphere.velocity = na::zero();
+			} else {
+				sphere.position = position;
+				sphere.velocity = velocity;
+			}
+		}
+		self.deform(dt);
+		self.tear();
+		self.limit_strain();
+	}
+
+	fn integrate_forces(&self, positions: & mut [Vec3<f32>], velocities: & mut [Vec3<f32>], dt: f32, laws: &Laws) {
+		let gravity = Vec3::new(0.0, laws.g, 0.0);
+		laws.integrator.integrate(positions, velocities, dt, |pos, vel| {
+			let mut forces = self.external_forces(pos);
+			volume::apply_volume_forces(self, laws.volume_stiffness, pos, & mut forces);
+			for conn in self.connections.iter() {
+				let (k, damp) = conn.params.resolve(laws.k, laws.dampening);
+				let force = spring_force(pos[conn.rhs] - pos[conn.lhs], vel[conn.rhs] - vel[conn.lhs], conn.starting_distance, k, damp, &laws.spring_force);
+				forces[conn.lhs] = forces[conn.lhs] - force;
+				forces[conn.rhs] = forces[conn.rhs] + force;
+			}
+			self.accelerations(&forces, gravity)
+		});
+	}
+
+	// accumulated point forces plus gas pressure, everything except the springs
+	fn external_forces(&self, pos: &[Vec3<f32>]) -> Vec<Vec3<f32>> {
+		let mut forces: Vec<Vec3<f32>> = self.points.iter().map(|p| p.force).collect();
+		match self.gas {
+			Some(gas) => apply_pressure_forces(&self.faces, gas, pos, & mut forces),
+			None => (),
+		}
+		forces
+	}
+
+	fn accelerations(&self, forces: &[Vec3<f32>], gravity: Vec3<f32>) -> Vec<Vec3<f32>> {
+		self.points.iter().zip(forces.iter()).map(|(p, f)| {
+			if p.fixed { na::zero() } else { *f / p.mass + gravity }
+		}).collect()
+	}
+
+	// moves the rest length of plastic springs strained past their yield toward the current length
+	fn deform(& mut self, dt: f32) {
+		for conn in self.connections.iter_mut() {
+			let plasticity = match conn.params.plasticity {
+				Some(plasticity) => plasticity,
+				None => continue,
+			};
+			let length = (self.points[conn.rhs].position - self.points[conn.lhs].position).norm();
+			let strain = (length - conn.starting_distance) / conn.starting_distance;
+			if strain.abs() <= plasticity.yield_strain {
+				continue;
+			}
+			let excess = (strain.abs() - plasticity.yield_strain) / strain.abs();
+			let amount = (plasticity.rate * dt).min(1.0) * excess;
+			conn.starting_distance += (length - conn.starting_distance) * amount;
+		}
+	}
+
+	// removes every breakable connection strained past its threshold
+	fn tear(& mut self) {
+		let mut index = 0;
+		while index < self.connections.len() {
+			let broken = {
+				let conn = &self.connections[index];
+				match conn.params.break_strain {
+					Some(break_strain) => {
+						let length = (self.points[conn.rhs].position - self.points[conn.lhs].position).norm();
+						let strain = (length - conn.starting_distance) / conn.starting_distance;
+						if strain > break_strain { Some(strain) } else { None }
+					},
+					None => None,
+				}
+			};
+			match broken {
+				Some(strain) => {
+					let conn = self.connections.swap_remove(index);
+					self.linked.remove(&link_key(conn.lhs, conn.rhs));
+					self.tear_events.push(TearEvent {
+						lhs: conn.lhs,
+						rhs: conn.rhs,
+						strain: strain,
+					});
+				},
+				None => index += 1,
+			}
+		}
+	}
+
+	/// Returns and clears the connections torn since the last call.
+	pub fn drain_tear_events(& mut self) -> Vec<TearEvent> {
+		::std::mem::replace(& mut self.tear_events, vec![])
+	}
+
+	// pulls the ends of over-strained springs back within their `max_strain`
+	// and removes the relative velocity that was straining them further
+	fn limit_strain(& mut self) {
+		for conn in self.connections.iter() {
+			let max_strain = match conn.params.max_strain {
+				Some(max_strain) => max_strain,
+				None => continue,
+			};
+			let (lhs, rhs) = self.points.get_pair_mut(conn.lhs, conn.rhs);
+			let offset = rhs.position - lhs.position;
+			let length = offset.norm();
+			let min_length = conn.starting_distance * (1.0 - max_strain).max(0.0);
+			let max_length = conn.starting_distance * (1.0 + max_strain);
+			let target = if length > max_length { max_length } else if length < min_length { min_length } else { continue };
+			if length == 0.0 {
+				continue;
+			}
+
+			let lhs_weight = if lhs.fixed { 0.0 } else { 1.0 / lhs.mass };
+			let rhs_weight = if rhs.fixed { 0.0 } else { 1.0 / rhs.mass };
+			let total_weight = lhs_weight + rhs_weight;
+			if total_weight == 0.0 {
+				continue;
+			}
+			let direction = offset / length;
+			let correction = direction * (length - target);
+			lhs.position = lhs.position + correction * (lhs_weight / total_weight);
+			rhs.position = rhs.position - correction * (rhs_weight / total_weight);
+
+			let separating = na::dot(&(rhs.velocity - lhs.velocity), &direction);
+			if (length > max_length && separating > 0.0) || (length < min_length && separating < 0.0) {
+				let impulse = direction * separating;
+				lhs.velocity = lhs.velocity + impulse * (lhs_weight / total_weight);
+				rhs.velocity = rhs.velocity - impulse * (rhs_weight / total_weight);
+			}
+		}
+	}
+
+	/// Whether points `lhs` and `rhs` are joined by a connection.
+	pub fn is_linked(&self, lhs: usize, rhs: usize) -> bool {
+		self.linked.contains(&link_key(lhs, rhs))
+	}
+
+	pub fn get_connections(&self) -> &[ConnectionData] {
+		&self.connections
+	}
+
+	/// Connections can be retuned in place, e.g. to give part of a body a different material.
+	pub fn get_connections_mut(& mut self) -> & mut [ConnectionData] {
+		& mut self.connections
+	}
+
+	pub fn get_points(&self) -> &Vec<Sphere> {
+		&self.points
+	}
+
+	pub fn get_points_mut(& mut self) -> & mut Vec<Sphere> {
+		& mut self.points
+	}
+}
+// force on the rhs end of a spring, `offset` and `rel_velocity` being rhs minus lhs
+fn spring_force(offset: Vec3<f32>, rel_velocity: Vec3<f32>, distance: f32, k: f32, damp: f32, mac: & VM) -> Vec3<f32> {
+	let curr_distance = offset.norm();
+	let mut force = offset.normalize();
+	let modifier = curr_distance - distance;
+	// x v d k
+	let data = vec![force.x as f64 * modifier as f64, rel_velocity.x as f64, damp as f64, k as f64];
+	force.x = mac.run(&data) as f32;
+	let data = vec![force.y as f64 * modifier as f64, rel_velocity.y as f64, damp as f64, k as f64];
+	force.y = mac.run(&data) as f32;
+	let data = vec![force.z as f64 * modifier as f64, rel_velocity.z as f64, damp as f64, k as f64];
+	force.z = mac.run(&data) as f32;
+	force
+}
+
+fn enclosed_volume(faces: &[[usize; 3]], pos: &[Vec3<f32>]) -> f32 {
+	faces.iter().fold(0.0, |volume, face| {
+		volume + na::dot(&pos[face[0]], &na::cross(&pos[face[1]], &pos[face[2]])) / 6.0
+	})
+}
+
+// pushes each surface triangle outward with the pressure of the enclosed gas, split between its corners
+fn apply_pressure_forces(faces: &[[usize; 3]], gas: f32, pos: &[Vec3<f32>], forces: & mut Vec<Vec3<f32>>) {
+	let volume = enclosed_volume(faces, pos);
+	if volume <= 0.0 {
+		return;
+	}
+	let pressure = gas / volume;
+	for face in faces.iter() {
+		// half the cross product is the area weighted normal
+		let area_normal = na::cross(&(pos[face[1]] - pos[face[0]]), &(pos[face[2]] - pos[face[0]])) * 0.5;
+		let force = area_normal * (pressure / 3.0);
+		for &corner in face.iter() {
+			forces[corner] = forces[corner] + force;
+		}
+	}
+}
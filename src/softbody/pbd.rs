@@ -0,0 +1,65 @@
+extern crate nalgebra as na;
+
+use na::*;
+use laws::*;
+use super::SoftBody;
+use super::volume;
+
+/// How a soft body enforces its springs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Solver {
+	// springs push and pull through forces handed to the integrator
+	Force,
+	// springs are XPBD distance constraints projected directly on positions
+	Position {
+		iterations: u32,
+	},
+}
+
+// XPBD: predict positions from external forces, project every distance constraint `iterations`
+// times with compliance 1 / k, along with any volume constraints, then derive velocities from how far the points actually moved
+pub fn solve_positions(body: &SoftBody, positions: & mut [Vec3<f32>], velocities: & mut [Vec3<f32>], dt: f32, iterations: u32, laws: &Laws) {
+	let gravity = Vec3::new(0.0, laws.g, 0.0);
+	let forces = body.external_forces(positions);
+	let accelerations = body.accelerations(&forces, gravity);
+	let weights: Vec<f32> = body.points.iter().map(|p| if p.fixed { 0.0 } else { 1.0 / p.mass }).collect();
+	let previous = positions.to_vec();
+	for i in 0..positions.len() {
+		if weights[i] == 0.0 {
+			continue;
+		}
+		velocities[i] = velocities[i] + accelerations[i] * dt;
+		positions[i] = positions[i] + velocities[i] * dt;
+	}
+
+	let mut lambdas = vec![0.0f32; body.connections.len()];
+	let mut volume_lambdas = vec![0.0f32; volume::constraint_count(body)];
+	for _ in 0..iterations {
+		volume::project_volumes(body, laws.volume_stiffness, dt, &weights, & mut volume_lambdas, positions);
+		for (conn, lambda) in body.connections.iter().zip(lambdas.iter_mut()) {
+			let total_weight = weights[conn.lhs] + weights[conn.rhs];
+			let offset = positions[conn.rhs] - positions[conn.lhs];
+			let length = offset.norm();
+			if total_weight == 0.0 || length == 0.0 {
+				continue;
+			}
+			let normal = offset / length;
+			let constraint = length - conn.starting_distance;
+
+			let (k, damp) = conn.params.resolve(laws.k, laws.dampening);
+			let compliance = 1.0 / k;
+			let scaled_compliance = compliance / (dt * dt);
+			let gamma = compliance * damp / dt;
+			let moved = na::dot(&normal, &((positions[conn.rhs] - previous[conn.rhs]) - (positions[conn.lhs] - previous[conn.lhs])));
+
+			let delta = (-constraint - scaled_compliance * *lambda - gamma * moved) / ((1.0 + gamma) * total_weight + scaled_compliance);
+			*lambda += delta;
+			positions[conn.lhs] = positions[conn.lhs] - normal * (weights[conn.lhs] * delta);
+			positions[conn.rhs] = positions[conn.rhs] + normal * (weights[conn.rhs] * delta);
+		}
+	}
+
+	for i in 0..positions.len() {
+		velocities[i] = (positions[i] - previous[i]) / dt;
+	}
+}
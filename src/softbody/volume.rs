@@ -0,0 +1,106 @@
+extern crate nalgebra as na;
+
+use na::*;
+use super::SoftBody;
+
+/// Resistance of a lattice soft body to being squashed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VolumeConstraint {
+	None,
+	// every tetrahedron of the lattice keeps its own volume
+	Tetrahedra,
+	// only the summed volume of all tetrahedra is kept
+	Global,
+}
+
+/// Four lattice points spanning one sixth of a cell.
+#[derive(Clone, Copy, Debug)]
+pub struct Tetrahedron {
+	pub corners: [usize; 4],
+	pub rest_volume: f32,
+}
+
+pub fn signed_volume(corners: &[usize; 4], pos: &[Vec3<f32>]) -> f32 {
+	let (a, b, c, d) = (pos[corners[0]], pos[corners[1]], pos[corners[2]], pos[corners[3]]);
+	na::dot(&(b - a), &na::cross(&(c - a), &(d - a))) / 6.0
+}
+
+// derivative of `signed_volume` with respect to each corner
+fn volume_gradients(corners: &[usize; 4], pos: &[Vec3<f32>]) -> [Vec3<f32>; 4] {
+	let (a, b, c, d) = (pos[corners[0]], pos[corners[1]], pos[corners[2]], pos[corners[3]]);
+	let grad_b = na::cross(&(c - a), &(d - a)) / 6.0;
+	let grad_c = na::cross(&(d - a), &(b - a)) / 6.0;
+	let grad_d = na::cross(&(b - a), &(c - a)) / 6.0;
+	[-(grad_b + grad_c + grad_d), grad_b, grad_c, grad_d]
+}
+
+// groups of tetrahedra each treated as a single volume constraint
+fn groups(body: &SoftBody) -> Vec<Vec<usize>> {
+	match body.volume_constraint {
+		VolumeConstraint::None => vec![],
+		VolumeConstraint::Tetrahedra => (0..body.tetrahedra.len()).map(|i| vec![i]).collect(),
+		VolumeConstraint::Global => vec![(0..body.tetrahedra.len()).collect()],
+	}
+}
+
+// volume, rest volume and per point gradient of one group
+fn measure(body: &SoftBody, group: &[usize], pos: &[Vec3<f32>]) -> (f32, f32, Vec<(usize, Vec3<f32>)>) {
+	let mut volume = 0.0;
+	let mut rest_volume = 0.0;
+	let mut gradients = Vec::with_capacity(group.len() * 4);
+	for &index in group.iter() {
+		let tetrahedron = &body.tetrahedra[index];
+		volume += signed_volume(&tetrahedron.corners, pos);
+		rest_volume += tetrahedron.rest_volume;
+		let grads = volume_gradients(&tetrahedron.corners, pos);
+		for corner in 0..4 {
+			gradients.push((tetrahedron.corners[corner], grads[corner]));
+		}
+	}
+	(volume, rest_volume, gradients)
+}
+
+// penalty forces proportional to the relative volume change, `stiffness` acting as a bulk modulus in Pa
+pub fn apply_volume_forces(body: &SoftBody, stiffness: f32, pos: &[Vec3<f32>], forces: & mut Vec<Vec3<f32>>) {
+	for group in groups(body).iter() {
+		let (volume, rest_volume, gradients) = measure(body, group, pos);
+		if rest_volume == 0.0 {
+			continue;
+		}
+		let strain = (volume - rest_volume) / rest_volume;
+		for &(point, gradient) in gradients.iter() {
+			forces[point] = forces[point] - gradient * (stiffness * strain);
+		}
+	}
+}
+
+// one XPBD pass over the volume constraints, with the same energy as `apply_volume_forces`
+pub fn project_volumes(body: &SoftBody, stiffness: f32, dt: f32, weights: &[f32], lambdas: & mut [f32], positions: & mut [Vec3<f32>]) {
+	for (group, lambda) in groups(body).iter().zip(lambdas.iter_mut()) {
+		let (volume, rest_volume, gradients) = measure(body, group, positions);
+		if rest_volume == 0.0 {
+			continue;
+		}
+		let scaled_compliance = rest_volume.abs() / stiffness / (dt * dt);
+		let denominator = gradients.iter().fold(scaled_compliance, |sum, &(point, gradient)| {
+			sum + weights[point] * na::sqnorm(&gradient)
+		});
+		if denominator == 0.0 {
+			continue;
+		}
+		let delta = (rest_volume - volume - scaled_compliance * *lambda) / denominator;
+		*lambda += delta;
+		for &(point, gradient) in gradients.iter() {
+			positions[point] = positions[point] + gradient * (weights[point] * delta);
+		}
+	}
+}
+
+/// Number of constraints `project_volumes` expects lambdas for.
+pub fn constraint_count(body: &SoftBody) -> usize {
+	match body.volume_constraint {
+		VolumeConstraint::None => 0,
+		VolumeConstraint::Tetrahedra => body.tetrahedra.len(),
+		VolumeConstraint::Global => if body.tetrahedra.is_empty() { 0 } else { 1 },
+	}
+}
@@ -0,0 +1,203 @@
+extern crate nalgebra as na;
+
+use std::collections::HashMap;
+
+use na::*;
+use broadphase::*;
+use collision::*;
+use material::*;
+use sphere::*;
+
+/// Velocity state of one sphere while contacts are being solved.
+#[derive(Clone, Debug)]
+pub struct SolverBody {
+	pub velocity: Vec3<f32>,
+	pub angular_velocity: Vec3<f32>,
+	inverse_mass: f32,
+	inverse_inertia: Mat3<f32>,
+}
+
+impl SolverBody {
+	pub fn from_sphere(sphere: &Sphere) -> SolverBody {
+		SolverBody {
+			velocity: sphere.velocity,
+			angular_velocity: sphere.angular_velocity,
+			inverse_mass: sphere.inverse_mass(),
+			inverse_inertia: sphere.inverse_inertia(),
+		}
+	}
+
+	fn velocity_at(&self, offset: Vec3<f32>) -> Vec3<f32> {
+		point_velocity(self.velocity, self.angular_velocity, offset)
+	}
+
+	fn inverse_mass_at(&self, offset: Vec3<f32>, direction: Vec3<f32>) -> f32 {
+		effective_inverse_mass(self.inverse_mass, &self.inverse_inertia, offset, direction)
+	}
+
+	fn apply_impulse(& mut self, impulse: Vec3<f32>, offset: Vec3<f32>) {
+		let (linear, angular) = impulse_response(self.inverse_mass, &self.inverse_inertia, impulse, offset);
+		self.velocity = self.velocity + linear;
+		self.angular_velocity = self.angular_velocity + angular;
+	}
+}
+
+/// Identifies a contact across steps so its impulses can warm start the next solve.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ContactKey {
+	Spheres(BodyRef, BodyRef),
+	// sphere, index among the planes followed by the other static colliders
+	Static(BodyRef, usize),
+}
+
+/// A touching pair: `lhs` and, unless it is static geometry, `rhs` index into the solver bodies.
+#[derive(Clone, Debug)]
+pub struct Contact {
+	pub key: ContactKey,
+	pub lhs: usize,
+	pub rhs: Option<usize>,
+	// points from rhs to lhs
+	pub normal: Vec3<f32>,
+	pub lhs_offset: Vec3<f32>,
+	pub rhs_offset: Vec3<f32>,
+	pub material: ContactMaterial,
+	// separating speed the collision law asks for, used in place of restitution when set
+	pub bounce: Option<f32>,
+	// velocity of static geometry's surface, which only kinematic colliders move
+	pub surface_velocity: Vec3<f32>,
+	target_speed: f32,
+	normal_impulse: f32,
+	tangent_impulse: Vec3<f32>,
+}
+
+impl Contact {
+	pub fn new(key: ContactKey, lhs: usize, rhs: Option<usize>, normal: Vec3<f32>, lhs_offset: Vec3<f32>, rhs_offset: Vec3<f32>,
+		material: ContactMaterial) -> Contact {
+		Contact {
+			key: key,
+			lhs: lhs,
+			rhs: rhs,
+			normal: normal,
+			lhs_offset: lhs_offset,
+			rhs_offset: rhs_offset,
+			material: material,
+			bounce: None,
+			surface_velocity: na::zero(),
+			target_speed: 0.0,
+			normal_impulse: 0.0,
+			tangent_impulse: na::zero(),
+		}
+	}
+
+	pub fn with_bounce(mut self, speed: f32) -> Contact {
+		self.bounce = Some(speed);
+		self
+	}
+
+	pub fn with_surface_velocity(mut self, velocity: Vec3<f32>) -> Contact {
+		self.surface_velocity = velocity;
+		self
+	}
+
+	fn relative_velocity(&self, bodies: &[SolverBody]) -> Vec3<f32> {
+		let lhs = bodies[self.lhs].velocity_at(self.lhs_offset);
+		match self.rhs {
+			Some(rhs) => lhs - bodies[rhs].velocity_at(self.rhs_offset),
+			None => lhs - self.surface_velocity,
+		}
+	}
+
+	fn inverse_mass(&self, bodies: &[SolverBody], direction: Vec3<f32>) -> f32 {
+		let lhs = bodies[self.lhs].inverse_mass_at(self.lhs_offset, direction);
+		match self.rhs {
+			Some(rhs) => lhs + bodies[rhs].inverse_mass_at(self.rhs_offset, direction),
+			None => lhs,
+		}
+	}
+
+	fn apply(&self, bodies: & mut [SolverBody], impulse: Vec3<f32>) {
+		bodies[self.lhs].apply_impulse(impulse, self.lhs_offset);
+		match self.rhs {
+			Some(rhs) => bodies[rhs].apply_impulse(-impulse, self.rhs_offset),
+			None => (),
+		}
+	}
+}
+
+/// Sequential impulse solver: every contact of a step is visited `iterations` times, each visit
+/// correcting the accumulated normal and friction impulses, so contacts that share a body settle
+/// together instead of undoing each other.
+pub struct ContactSolver {
+	pub iterations: u32,
+	// approach speed below which contacts stop instead of bouncing, see `resting_speed`
+	pub resting_speed: f32,
+	// fraction of last step's impulses applied before iterating
+	pub warm_start_factor: f32,
+	warm_start: HashMap<ContactKey, (f32, Vec3<f32>)>,
+}
+
+impl ContactSolver {
+	pub fn new(iterations: u32) -> ContactSolver {
+		ContactSolver {
+			iterations: iterations,
+			resting_speed: 0.0,
+			warm_start_factor: 0.8,
+			warm_start: HashMap::new(),
+		}
+	}
+
+	pub fn solve(& mut self, bodies: & mut [SolverBody], contacts: & mut [Contact]) {
+		for contact in contacts.iter_mut() {
+			let approach = na::dot(&contact.relative_velocity(bodies), &contact.normal);
+			contact.target_speed = if approach < -self.resting_speed {
+				contact.bounce.unwrap_or(-approach * contact.material.restitution).max(0.0)
+			} else {
+				0.0
+			};
+
+			match self.warm_start.get(&contact.key) {
+				Some(&(normal_impulse, tangent_impulse)) => {
+					// friction from last step only carries over if it still lies in the contact plane
+					let tangent_impulse = tangent_impulse - contact.normal * na::dot(&tangent_impulse, &contact.normal);
+					contact.normal_impulse = normal_impulse * self.warm_start_factor;
+					contact.tangent_impulse = tangent_impulse * self.warm_start_factor;
+					let impulse = contact.normal * contact.normal_impulse + contact.tangent_impulse;
+					contact.apply(bodies, impulse);
+				},
+				None => (),
+			}
+		}
+
+		for _ in 0..self.iterations {
+			for contact in contacts.iter_mut() {
+				// normal: push until the contact separates at its target speed, never pull
+				let speed = na::dot(&contact.relative_velocity(bodies), &contact.normal);
+				let inverse_mass = contact.inverse_mass(bodies, contact.normal);
+				if inverse_mass > 0.0 {
+					let previous = contact.normal_impulse;
+					contact.normal_impulse = (previous + (contact.target_speed - speed) / inverse_mass).max(0.0);
+					let impulse = contact.normal * (contact.normal_impulse - previous);
+					contact.apply(bodies, impulse);
+				}
+
+				// friction: stop the slide, keeping the accumulated impulse within the Coulomb cone
+				// of the current normal impulse
+				let stopping = {
+					let contact = &*contact;
+					let bodies = &*bodies;
+					stopping_impulse(contact.relative_velocity(bodies), contact.normal,
+						|direction| contact.inverse_mass(bodies, direction))
+				};
+				let previous = contact.tangent_impulse;
+				contact.tangent_impulse = clamp_friction(previous + stopping, contact.normal_impulse, &contact.material);
+				let change = contact.tangent_impulse - previous;
+				contact.apply(bodies, change);
+			}
+		}
+
+		self.warm_start.clear();
+		for contact in contacts.iter() {
+			self.warm_start.insert(contact.key, (contact.normal_impulse, contact.tangent_impulse));
+		}
+	}
+}
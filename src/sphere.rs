@@ -1,30 +1,55 @@
-extern crate glutin;
-
-extern crate glium;
-
 extern crate nalgebra as na;
 
-use std::f32::consts::PI;
 use na::*;
-
-#[derive(Clone, Copy)]
-pub struct Vertex {
-    position: [f32; 3],
-}
-
-implement_vertex!(Vertex, position);
-
+use integrator::*;
+use material::*;
+use shape::*;
 
 #[derive(Clone, Debug)]
 pub struct Sphere {
     pub mass: f32,
+    // bounds the shape, which for a ball it is the radius of
     pub radius: f32,
+    pub shape: Shape,
     pub position: Vec3<f32>,
     pub velocity: Vec3<f32>,
     pub force: Vec3<f32>,
     pub rotation: Rot3<f32>,
     pub angular_velocity: Vec3<f32>,
+    // body space inertia tensor
+    pub inertia: Mat3<f32>,
+    pub torque: Vec3<f32>,
+    pub material: Material,
     pub fixed: bool,
+    // asleep spheres are neither integrated nor tested against planes or other sleepers
+    pub sleeping: bool,
+    // consecutive steps spent slower than the sleep speed
+    still_steps: u32,
+}
+
+/// Inertia tensor of a solid ball.
+pub fn solid_sphere_inertia(radius: f32, mass: f32) -> Mat3<f32> {
+    let moment = 0.4 * mass * radius * radius;
+    Mat3::new(moment, 0.0, 0.0,
+              0.0, moment, 0.0,
+              0.0, 0.0, moment)
+}
+
+/// Velocity of the point `offset` from the centre of a body moving at `velocity` and spinning at `angular_velocity`.
+pub fn point_velocity(velocity: Vec3<f32>, angular_velocity: Vec3<f32>, offset: Vec3<f32>) -> Vec3<f32> {
+    velocity + na::cross(&angular_velocity, &offset)
+}
+
+/// Change in the velocity at `offset`, along `direction`, per unit impulse applied there along `direction`,
+/// for a body with the given inverse mass and world space inverse inertia.
+pub fn effective_inverse_mass(inverse_mass: f32, inverse_inertia: &Mat3<f32>, offset: Vec3<f32>, direction: Vec3<f32>) -> f32 {
+    let angular = na::cross(&(*inverse_inertia * na::cross(&offset, &direction)), &offset);
+    inverse_mass + na::dot(&angular, &direction)
+}
+
+/// Changes in linear and angular velocity from `impulse` applied at `offset` from the centre.
+pub fn impulse_response(inverse_mass: f32, inverse_inertia: &Mat3<f32>, impulse: Vec3<f32>, offset: Vec3<f32>) -> (Vec3<f32>, Vec3<f32>) {
+    (impulse * inverse_mass, *inverse_inertia * na::cross(&offset, &impulse))
 }
 
 impl Sphere {
@@ -32,120 +57,155 @@ impl Sphere {
         Sphere {
             mass: mass,
             radius: radius,
+            shape: Shape::Ball(radius),
             position: na::zero(),
             velocity: na::zero(),
             force: na::zero(),
             rotation: Rot3::new(na::zero()),
             angular_velocity: na::zero(),
+            inertia: solid_sphere_inertia(radius, mass),
+            torque: na::zero(),
+            material: Material::default(),
             fixed: false,
+            sleeping: false,
+            still_steps: 0,
         }
     }
 
-    // todo remove self from all methods
-    // set size to 1
-    fn get_points(subdivs: u32) -> Vec<Vec3<f32>> {
-        let mut points: Vec<Vec3<f32>> = vec![];
-        let y_subdivs = subdivs;
-        let x_subdivs = subdivs * 2;
-
-        let y_angle_iter = (0..y_subdivs)
-            .map(|a| a * (90 / y_subdivs) )
-            .map(|a| a as f32 / 180.0f32 * PI );
-        let next_y_angle_iter =(1..y_subdivs + 1)
-            .map(|a| a * (90 / y_subdivs) )
-            .map(|a| a as f32 / 180.0f32 * PI );
-
-        for (y_angle, next_y_angle) in y_angle_iter.zip(next_y_angle_iter)
-        {
-            let y_dist = y_angle.cos() * 1.0;
-            let xz_dist = y_angle.sin() * 1.0;
-
-            let next_y_dist = next_y_angle.cos() * 1.0;
-            let next_xz_dist = next_y_angle.sin() * 1.0;
-
-            let xz_angle_iter = (0..x_subdivs)
-                .map(|a| a * 180 / x_subdivs )
-                .map(|a| a as f32 / 180.0 * PI );
-            let next_xz_angle_iter = (1..x_subdivs + 1)
-                .map(|a| a * 180 / x_subdivs )
-                .map(|a| a as f32 / 180.0 * PI );
-
-            for (xz_angle, next_xz_angle) in xz_angle_iter.zip(next_xz_angle_iter)
-            {
-                let tl_x_dist = xz_angle.cos() * xz_dist;
-                let tl_z_dist = xz_angle.sin() * xz_dist;
-
-                let tr_x_dist = next_xz_angle.cos() * xz_dist;
-                let tr_z_dist = next_xz_angle.sin() * xz_dist;
-
-                let bl_x_dist = xz_angle.cos() * next_xz_dist;
-                let bl_z_dist = xz_angle.sin() * next_xz_dist;
-
-                let br_x_dist = next_xz_angle.cos() * next_xz_dist;
-                let br_z_dist = next_xz_angle.sin() * next_xz_dist;
-
-                let mut make_face = |tl_x, t_y, tl_z, tr_x, tr_z, bl_x, b_y, bl_z, br_x, br_z| {
-                    let top_left = Vec3::new(tl_x, t_y, tl_z);
-                    let top_right = Vec3::new(tr_x, t_y, tr_z);
-                    let bottom_left = Vec3::new(bl_x, b_y, bl_z);
-                    let bottom_right = Vec3::new(br_x, b_y, br_z);
-
-                    points.push(top_left);
-                    points.push(top_right);
-                    points.push(bottom_left);
-
-                    points.push(top_right);
-                    points.push(bottom_right);
-                    points.push(bottom_left);
-
-                };
-
-                make_face(tl_x_dist, y_dist, tl_z_dist, 
-                    tr_x_dist, tr_z_dist, 
-                    bl_x_dist, next_y_dist, bl_z_dist, 
-                    br_x_dist, br_z_dist);
-                make_face(tl_x_dist, -y_dist, tl_z_dist, 
-                    tr_x_dist, tr_z_dist, 
-                    bl_x_dist, -next_y_dist, bl_z_dist, 
-                    br_x_dist, br_z_dist);
-
-                make_face(tl_x_dist, y_dist, -tl_z_dist, 
-                    tr_x_dist, -tr_z_dist, 
-                    bl_x_dist, next_y_dist, -bl_z_dist, 
-                    br_x_dist, -br_z_dist);
-                make_face(tl_x_dist, -y_dist, -tl_z_dist, 
-                    tr_x_dist, -tr_z_dist, 
-                    bl_x_dist, -next_y_dist, -bl_z_dist, 
-                    br_x_dist, -br_z_dist);
-            }
+    /// A sphere whose mass follows from the material's density.
+    pub fn with_material(radius: f32, material: Material) -> Sphere {
+        let volume = 4.0 / 3.0 * ::std::f32::consts::PI * radius * radius * radius;
+        let mut sphere = Sphere::new(radius, material.density * volume);
+        sphere.material = material;
+        sphere
+    }
+
+    /// A body of any shape, bounded by the shape's bounding radius.
+    pub fn with_shape(shape: Shape, mass: f32) -> Sphere {
+        let mut sphere = Sphere::new(shape.bounding_radius(), mass);
+        sphere.inertia = shape.inertia(mass);
+        sphere.shape = shape;
+        sphere
+    }
+
+    pub fn inverse_mass(&self) -> f32 {
+        if self.fixed { 0.0 } else { 1.0 / self.mass }
+    }
+
+    /// World space inverse inertia tensor, zero for fixed spheres.
+    pub fn inverse_inertia(&self) -> Mat3<f32> {
+        if self.fixed {
+            return na::zero();
         }
-        points
+        let rotation = *self.rotation.submat();
+        let body_inverse = na::inv(&self.inertia).unwrap_or(na::zero());
+        rotation * body_inverse * na::transpose(&rotation)
+    }
+
+    /// World space point of the body's surface furthest along `direction`.
+    pub fn support(&self, direction: Vec3<f32>) -> Vec3<f32> {
+        let rotation = *self.rotation.submat();
+        let local = na::transpose(&rotation) * direction;
+        self.position + rotation * self.shape.support(local)
     }
-    fn into_vertex_list(subdivs: u32) -> Vec<Vertex> {
-        Self::get_points(subdivs).iter().map(|pt| {
-            Vertex { position: pt.as_array().clone()}
-        }).collect()
+
+    /// Offset from the centre to the surface point furthest along `direction`, where contacts in that direction touch.
+    pub fn surface_offset(&self, direction: Vec3<f32>) -> Vec3<f32> {
+        self.support(direction) - self.position
+    }
+
+    /// Offset from the centre to where a contact in `direction` pushes: the middle of a face or edge
+    /// lying flat that way, so resting on one doesn't spin the body.
+    pub fn contact_offset(&self, direction: Vec3<f32>) -> Vec3<f32> {
+        let rotation = *self.rotation.submat();
+        let local = na::transpose(&rotation) * direction;
+        rotation * self.shape.contact_support(local)
+    }
+
+    /// Velocity of the point `offset` away from the centre.
+    pub fn velocity_at(&self, offset: Vec3<f32>) -> Vec3<f32> {
+        point_velocity(self.velocity, self.angular_velocity, offset)
+    }
+
+    /// Change in the velocity at `offset`, along `direction`, per unit impulse applied there along `direction`.
+    pub fn inverse_mass_at(&self, offset: Vec3<f32>, direction: Vec3<f32>) -> f32 {
+        effective_inverse_mass(self.inverse_mass(), &self.inverse_inertia(), offset, direction)
+    }
+
+    /// Applies `impulse` at `offset` from the centre, changing both linear and angular velocity.
+    pub fn apply_impulse(&mut self, impulse: Vec3<f32>, offset: Vec3<f32>) {
+        let (linear, angular) = impulse_response(self.inverse_mass(), &self.inverse_inertia(), impulse, offset);
+        self.velocity = self.velocity + linear;
+        self.angular_velocity = self.angular_velocity + angular;
     }
-    pub fn into_buffer<F>(display: &F, subdivs: u32) -> glium::VertexBuffer<Vertex> where F: glium::backend::Facade {
-        glium::VertexBuffer::new(display, Self::into_vertex_list(subdivs))
+
+    /// Accumulates `force` acting at `offset` from the centre into `force` and `torque`.
+    pub fn apply_force_at(&mut self, force: Vec3<f32>, offset: Vec3<f32>) {
+        self.force = self.force + force;
+        self.torque = self.torque + na::cross(&offset, &force);
     }
-    // until here
 
-    pub fn update(&mut self) {
-        self.rotation = Rot3::new(self.angular_velocity) * self.rotation;
+    /// Wakes a sleeping sphere; an awake one keeps counting towards sleep, so resting contacts don't hold it awake.
+    pub fn wake(&mut self) {
+        if self.sleeping {
+            self.sleeping = false;
+            self.still_steps = 0;
+        }
+    }
+
+    /// Counts the steps the sphere has stayed slower than `speed`, measuring spin at its surface,
+    /// and puts it to sleep once that reaches `steps`. A `steps` of 0 never sleeps.
+    pub fn settle(&mut self, speed: f32, steps: u32) {
+        if self.sleeping || self.fixed || steps == 0 {
+            return;
+        }
+        let spin = self.angular_velocity.norm() * self.radius;
+        if self.velocity.norm() < speed && spin < speed {
+            self.still_steps += 1;
+        } else {
+            self.still_steps = 0;
+        }
+        if self.still_steps >= steps {
+            self.sleeping = true;
+            self.velocity = na::zero();
+            self.angular_velocity = na::zero();
+        }
+    }
+
+    /// Advances the sphere by `dt` seconds, consuming the accumulated `force` and `torque`.
+    /// `gravity` is added to the acceleration the force produces.
+    /// A sleeping sphere stays put unless a force or torque has been applied, which wakes it.
+    pub fn update(&mut self, dt: f32, integrator: &Integrator, gravity: Vec3<f32>) {
+        if self.sleeping {
+            if self.force == na::zero() && self.torque == na::zero() {
+                return;
+            }
+            self.wake();
+        }
+        self.angular_velocity = self.angular_velocity + self.inverse_inertia() * self.torque * dt;
+        self.torque = na::zero();
+        self.rotation = Rot3::new(self.angular_velocity * dt) * self.rotation;
         if !self.fixed {
-            self.velocity = self.velocity + self.force / self.mass;
+            let accel = self.force / self.mass + gravity;
+            let mut position = [self.position];
+            let mut velocity = [self.velocity];
+            integrator.integrate(& mut position, & mut velocity, dt, |_, _| vec![accel]);
+            self.position = position[0];
+            self.velocity = velocity[0];
             self.force.x = 0.0;
             self.force.y = 0.0;
             self.force.z = 0.0;
-            self.position = self.position + self.velocity;
         } else {
             self.velocity = na::zero();
         }
     }
     pub fn get_homogeneous(&self) -> na::Mat4<f32> {
+        self.get_homogeneous_at(self.position)
+    }
+    /// Same as `get_homogeneous`, but drawn at `position` instead of the simulated one.
+    pub fn get_homogeneous_at(&self, position: Vec3<f32>) -> na::Mat4<f32> {
         let mut scale_mat: Mat4<f32> = na::one::<Mat4<_>>() * self.radius;
         scale_mat.m44 = 1.0;
-        na::Iso3::new_with_rotmat(self.position, self.rotation).to_homogeneous() * scale_mat
+        na::Iso3::new_with_rotmat(position, self.rotation).to_homogeneous() * scale_mat
     }
 }
\ No newline at end of file
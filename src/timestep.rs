@@ -0,0 +1,63 @@
+extern crate nalgebra as na;
+
+use na::*;
+use world::*;
+
+/// Drives a `World` at a fixed rate independent of how often frames are drawn.
+/// Real time is accumulated and consumed in whole steps of `dt`, each split into `substeps` world steps.
+pub struct FixedStep {
+	pub dt: f32,
+	pub substeps: u32,
+	// most steps taken in one call to `advance`, so a slow frame can't snowball
+	pub max_steps: u32,
+	accumulator: f32,
+	previous: Vec<Vec3<f32>>,
+}
+
+impl FixedStep {
+	pub fn new(dt: f32, substeps: u32) -> FixedStep {
+		FixedStep {
+			dt: dt,
+			substeps: if substeps == 0 { 1 } else { substeps },
+			max_steps: 8,
+			accumulator: 0.0,
+			previous: vec![],
+		}
+	}
+
+	/// Adds `frame_time` seconds to the accumulator and runs every whole step that fits.
+	/// Returns how far, from 0 to 1, the leftover time is into the next step.
+	pub fn advance(& mut self, world: & mut World, frame_time: f32) -> f32 {
+		self.accumulator += frame_time;
+		if self.previous.len() != world.positions().len() {
+			self.previous = world.positions();
+		}
+
+		let mut steps = 0;
+		while self.accumulator >= self.dt {
+			if steps == self.max_steps {
+				self.accumulator = 0.0;
+				break;
+			}
+			self.previous = world.positions();
+			for _ in 0..self.substeps {
+				world.step(self.dt / self.substeps as f32);
+			}
+			self.accumulator -= self.dt;
+			steps += 1;
+		}
+		self.accumulator / self.dt
+	}
+
+	/// Positions of the world's spheres, in `World::positions` order, blended `alpha` of the way
+	/// from the state before the last step to the current one.
+	pub fn interpolate(&self, world: &World, alpha: f32) -> Vec<Vec3<f32>> {
+		let current = world.positions();
+		if self.previous.len() != current.len() {
+			return current;
+		}
+		self.previous.iter().zip(current.iter()).map(|(prev, curr)| {
+			*prev + (*curr - *prev) * alpha
+		}).collect()
+	}
+}
@@ -0,0 +1,506 @@
+extern crate nalgebra as na;
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use na::*;
+use broadphase::*;
+use collider::*;
+use collision::*;
+use laws::*;
+use plane::*;
+use shape::*;
+use softbody::*;
+use solver::*;
+use sphere::*;
+use vec_tools::*;
+use vm::*;
+
+// most impacts between the members of a rewound island followed through the rest of a step
+const MAX_IMPACTS: usize = 8;
+
+// rigid balls rewound together because they hit one another during the last step: every member is
+// moved back to the island's earliest impact, with `remaining` seconds of the step still to go
+struct Island {
+	bodies: Vec<usize>,
+	remaining: f32,
+}
+
+/// Owns every body in a simulation and advances them together.
+pub struct World {
+	pub laws: Laws,
+	pub bodies: Vec<Sphere>,
+	pub planes: Vec<Plane>,
+	pub colliders: Vec<Box<StaticCollider>>,
+	pub soft_bodies: Vec<SoftBody>,
+	// indices into `bodies` of the sphere pairs that touched during the last step
+	contacts: Vec<(usize, usize)>,
+	broadphase: SweepAndPrune,
+	contact_solver: ContactSolver,
+	// seconds simulated so far, which drives kinematic colliders
+	time: f32,
+}
+
+impl World {
+	pub fn new(laws: Laws) -> World {
+		let contact_solver = ContactSolver::new(laws.solver_iterations);
+		World {
+			laws: laws,
+			bodies: vec![],
+			planes: vec![],
+			colliders: vec![],
+			soft_bodies: vec![],
+			contacts: vec![],
+			broadphase: SweepAndPrune::new(),
+			contact_solver: contact_solver,
+			time: 0.0,
+		}
+	}
+
+	pub fn add_body(& mut self, body: Sphere) -> usize {
+		self.bodies.push(body);
+		self.bodies.len() - 1
+	}
+
+	pub fn add_plane(& mut self, plane: Plane) -> usize {
+		self.planes.push(plane);
+		self.planes.len() - 1
+	}
+
+	pub fn add_collider<C: StaticCollider + 'static>(& mut self, collider: C) -> usize {
+		self.colliders.push(Box::new(collider));
+		self.colliders.len() - 1
+	}
+
+	pub fn add_soft_body(& mut self, soft_body: SoftBody) -> usize {
+		self.soft_bodies.push(soft_body);
+		self.soft_bodies.len() - 1
+	}
+
+	pub fn time(&self) -> f32 {
+		self.time
+	}
+
+	pub fn contacts(&self) -> &Vec<(usize, usize)> {
+		&self.contacts
+	}
+
+	/// Returns and clears, for every soft body index, the connections torn since the last call.
+	pub fn drain_tear_events(& mut self) -> Vec<(usize, TearEvent)> {
+		let mut events = vec![];
+		for (index, soft_body) in self.soft_bodies.iter_mut().enumerate() {
+			events.extend(soft_body.drain_tear_events().into_iter().map(|e| (index, e)));
+		}
+		events
+	}
+
+	/// Positions of every body followed by the points of each soft body in turn.
+	pub fn positions(&self) -> Vec<Vec3<f32>> {
+		let mut positions: Vec<_> = self.bodies.iter().map(|s| s.position).collect();
+		for soft_body in self.soft_bodies.iter() {
+			positions.extend(soft_body.get_points().iter().map(|s| s.position));
+		}
+		positions
+	}
+
+	/// Advances the simulation by `dt` seconds.
+	pub fn step(& mut self, dt: f32) {
+		{
+			let laws = &self.laws;
+			let gravity = Vec3::new(0.0, laws.g, 0.0);
+
+			for s in self.bodies.iter_mut() {
+				s.update(dt, &laws.integrator, gravity);
+			}
+			//softbody particle update
+			for soft_body in self.soft_bodies.iter_mut() {
+				soft_body.update(dt, laws);
+			}
+		}
+
+		self.time += dt;
+		for collider in self.colliders.iter_mut() {
+			collider.advance(self.time, dt);
+		}
+
+		let pairs = self.candidate_pairs(dt);
+		let (swept, islands) = self.rewind_to_impacts(&pairs, dt);
+		if self.laws.solver_iterations == 0 {
+			self.resolve_pairs(pairs, &swept, &islands, dt);
+		} else {
+			self.solve_contacts(pairs, &swept, &islands, dt);
+		}
+
+		for s in self.bodies.iter_mut() {
+			s.settle(self.laws.sleep_speed, self.laws.sleep_steps);
+		}
+	}
+
+	// broadphase pairs worth a narrow test, each ordered lhs < rhs and sorted so they resolve
+	// in a stable order regardless of how the broadphase found them
+	fn candidate_pairs(& mut self, dt: f32) -> Vec<(BodyRef, BodyRef)> {
+		self.broadphase.clear();
+		for (index, s) in self.bodies.iter().enumerate() {
+			self.broadphase.insert_swept(BodyRef::Body(index), s, dt);
+		}
+		for (body_index, soft_body) in self.soft_bodies.iter().enumerate() {
+			for (index, s) in soft_body.get_points().iter().enumerate() {
+				self.broadphase.insert(BodyRef::Point(body_index, index), s);
+			}
+		}
+
+		let mut pairs = vec![];
+		for (lhs, rhs) in self.broadphase.pairs() {
+			match (lhs, rhs) {
+				(BodyRef::Point(l_soft, l_index), BodyRef::Point(r_soft, r_index)) => {
+					if l_soft != r_soft {
+						continue;
+					}
+					let soft_body = &self.soft_bodies[l_soft];
+					if !soft_body.self_collision || soft_body.is_linked(l_index, r_index) {
+						continue;
+					}
+				},
+				// sleepers only wake when something awake runs into them
+				(BodyRef::Body(l_index), BodyRef::Body(r_index)) => {
+					if self.bodies[l_index].sleeping && self.bodies[r_index].sleeping {
+						continue;
+					}
+				},
+				_ => (),
+			}
+			pairs.push(if lhs < rhs { (lhs, rhs) } else { (rhs, lhs) });
+		}
+		pairs.sort();
+		pairs
+	}
+
+	// groups rigid balls that met during the last `dt` into islands and rewinds each island as a whole
+	// to its earliest impact, so fast pairs meet instead of passing through each other and every member
+	// is at the same moment; gives the pairs that met that way along with the islands
+	fn rewind_to_impacts(& mut self, pairs: &[(BodyRef, BodyRef)], dt: f32) -> (HashSet<(BodyRef, BodyRef)>, Vec<Island>) {
+		let mut swept = HashSet::new();
+		let mut parent: Vec<usize> = (0..self.bodies.len()).collect();
+		let mut impacts = vec![];
+		for &(l_ref, r_ref) in pairs.iter() {
+			let (li, ri) = match (l_ref, r_ref) {
+				(BodyRef::Body(li), BodyRef::Body(ri)) => (li, ri),
+				_ => continue,
+			};
+			let (lhs, rhs) = (&self.bodies[li], &self.bodies[ri]);
+			if !is_ball(lhs) || !is_ball(rhs) {
+				continue;
+			}
+			match time_of_impact(lhs, rhs, dt) {
+				Some(time) => {
+					swept.insert((l_ref, r_ref));
+					impacts.push((li, time));
+					let (l_root, r_root) = (find_root(& mut parent, li), find_root(& mut parent, ri));
+					parent[l_root] = r_root;
+				},
+				None => (),
+			}
+		}
+
+		let mut rewind: Vec<Option<f32>> = vec![None; self.bodies.len()];
+		for &(index, time) in impacts.iter() {
+			let root = find_root(& mut parent, index);
+			rewind[root] = Some(rewind[root].unwrap_or(0.0).max(dt - time));
+		}
+		let mut islands: Vec<Island> = vec![];
+		let mut island_of: Vec<Option<usize>> = vec![None; self.bodies.len()];
+		for index in 0..self.bodies.len() {
+			let root = find_root(& mut parent, index);
+			let remaining = match rewind[root] {
+				Some(remaining) => remaining,
+				None => continue,
+			};
+			if island_of[root].is_none() {
+				island_of[root] = Some(islands.len());
+				islands.push(Island { bodies: vec![], remaining: remaining });
+			}
+			islands[island_of[root].unwrap()].bodies.push(index);
+			let s = & mut self.bodies[index];
+			s.position = s.position - s.velocity * remaining;
+		}
+		(swept, islands)
+	}
+
+	// resolves every contact on its own with the collision law, planes last
+	fn resolve_pairs(& mut self, pairs: Vec<(BodyRef, BodyRef)>, swept: &HashSet<(BodyRef, BodyRef)>, islands: &[Island], dt: f32) {
+		let laws = &self.laws;
+		let resting = resting_speed(laws.g, dt);
+
+		self.contacts.clear();
+		for (l_ref, r_ref) in pairs {
+			let (lhs, rhs) = sphere_pair_mut(& mut self.bodies, & mut self.soft_bodies, l_ref, r_ref);
+			let result = match narrow_test(lhs, rhs, swept.contains(&(l_ref, r_ref))) {
+				Some(x) => x,
+				None => continue,
+			};
+			resolve_collision(lhs, rhs, result, &laws.collision_response);
+			lhs.wake();
+			rhs.wake();
+			match (l_ref, r_ref) {
+				(BodyRef::Body(li), BodyRef::Body(ri)) => self.contacts.push((li, ri)),
+				_ => (),
+			}
+		}
+		finish_step(& mut self.bodies, islands, &laws.collision_response);
+
+		for collider in static_colliders(&self.planes, &self.colliders) {
+			for s in self.bodies.iter_mut() {
+				wake_if_carried(collider, s, dt);
+				if !s.sleeping && collider.check_collision(s, dt) {
+					collider.bounce_sphere(s, resting);
+				}
+			}
+			for soft_body in self.soft_bodies.iter_mut() {
+				for s in soft_body.get_points_mut().iter_mut() {
+					if collider.check_collision(s, dt) {
+						collider.bounce_sphere(s, resting);
+					}
+				}
+			}
+		}
+	}
+
+	// gathers every touching pair, separates them, then lets the contact solver settle all velocities together
+	fn solve_contacts(& mut self, pairs: Vec<(BodyRef, BodyRef)>, swept: &HashSet<(BodyRef, BodyRef)>, islands: &[Island], dt: f32) {
+		// solver bodies are the spheres followed by each soft body's points, as in `positions`
+		let mut first_point = vec![];
+		let mut count = self.bodies.len();
+		for soft_body in self.soft_bodies.iter() {
+			first_point.push(count);
+			count += soft_body.get_points().len();
+		}
+		let index_of = |body: BodyRef| match body {
+			BodyRef::Body(index) => index,
+			BodyRef::Point(soft_index, index) => first_point[soft_index] + index,
+		};
+
+		let mut contacts = vec![];
+		self.contacts.clear();
+		for (l_ref, r_ref) in pairs {
+			let (lhs, rhs) = sphere_pair_mut(& mut self.bodies, & mut self.soft_bodies, l_ref, r_ref);
+			let result = match narrow_test(lhs, rhs, swept.contains(&(l_ref, r_ref))) {
+				Some(x) => x,
+				None => continue,
+			};
+			lhs.wake();
+			rhs.wake();
+			let (lhs_offset, rhs_offset) = (result.point - lhs.position, result.point - rhs.position);
+			separate(lhs, rhs, &result);
+			let material = lhs.material.combine(&rhs.material);
+			let bounce = law_separating_speed(lhs, rhs, result.normal, &material, &self.laws.collision_response);
+			contacts.push(Contact::new(ContactKey::Spheres(l_ref, r_ref), index_of(l_ref), Some(index_of(r_ref)), result.normal,
+				lhs_offset, rhs_offset, material).with_bounce(bounce));
+			match (l_ref, r_ref) {
+				(BodyRef::Body(li), BodyRef::Body(ri)) => self.contacts.push((li, ri)),
+				_ => (),
+			}
+		}
+
+		for (collider_index, collider) in static_colliders(&self.planes, &self.colliders).into_iter().enumerate() {
+			for (index, s) in self.bodies.iter_mut().enumerate() {
+				wake_if_carried(collider, s, dt);
+				if !s.sleeping && collider.check_collision(s, dt) {
+					let body = BodyRef::Body(index);
+					let normal = collider.normal_at(s);
+					let surface_velocity = collider.velocity_at(s.support(-normal));
+					contacts.push(Contact::new(ContactKey::Static(body, collider_index), index_of(body), None, normal,
+						s.contact_offset(-normal), na::zero(), collider.material().combine(&s.material))
+						.with_surface_velocity(surface_velocity));
+				}
+			}
+			for (soft_index, soft_body) in self.soft_bodies.iter_mut().enumerate() {
+				for (index, s) in soft_body.get_points_mut().iter_mut().enumerate() {
+					if collider.check_collision(s, dt) {
+						let body = BodyRef::Point(soft_index, index);
+						let normal = collider.normal_at(s);
+						let surface_velocity = collider.velocity_at(s.support(-normal));
+						contacts.push(Contact::new(ContactKey::Static(body, collider_index), index_of(body), None, normal,
+							s.contact_offset(-normal), na::zero(), collider.material().combine(&s.material))
+							.with_surface_velocity(surface_velocity));
+					}
+				}
+			}
+		}
+
+		let mut solver_bodies: Vec<_> = self.bodies.iter().map(SolverBody::from_sphere).collect();
+		for soft_body in self.soft_bodies.iter() {
+			solver_bodies.extend(soft_body.get_points().iter().map(SolverBody::from_sphere));
+		}
+
+		self.contact_solver.iterations = self.laws.solver_iterations;
+		self.contact_solver.resting_speed = resting_speed(self.laws.g, dt);
+		self.contact_solver.solve(& mut solver_bodies, & mut contacts);
+
+		let mut solved = solver_bodies.into_iter();
+		for s in self.bodies.iter_mut() {
+			let body = solved.next().unwrap();
+			s.velocity = body.velocity;
+			s.angular_velocity = body.angular_velocity;
+		}
+		for soft_body in self.soft_bodies.iter_mut() {
+			for s in soft_body.get_points_mut().iter_mut() {
+				let body = solved.next().unwrap();
+				s.velocity = body.velocity;
+				s.angular_velocity = body.angular_velocity;
+			}
+		}
+
+		// rewound bodies were only checked against static geometry where they met, so check again where they end up
+		finish_step(& mut self.bodies, islands, &self.laws.collision_response);
+		let resting = resting_speed(self.laws.g, dt);
+		for collider in static_colliders(&self.planes, &self.colliders) {
+			for island in islands.iter() {
+				for &index in island.bodies.iter() {
+					let s = & mut self.bodies[index];
+					if collider.check_collision(s, dt) {
+						collider.bounce_sphere(s, resting);
+					}
+				}
+			}
+		}
+	}
+
+	/// Writes the position and velocity of every body and soft body point, one per line.
+	pub fn write_state<W: Write>(&self, out: & mut W) -> ::std::io::Result<()> {
+		for (index, s) in self.bodies.iter().enumerate() {
+			try!(writeln!(out, "sphere {} position {} {} {} velocity {} {} {}", index,
+				s.position.x, s.position.y, s.position.z,
+				s.velocity.x, s.velocity.y, s.velocity.z));
+		}
+		for (body_index, soft_body) in self.soft_bodies.iter().enumerate() {
+			for (index, s) in soft_body.get_points().iter().enumerate() {
+				try!(writeln!(out, "softbody {} point {} position {} {} {} velocity {} {} {}", body_index, index,
+					s.position.x, s.position.y, s.position.z,
+					s.velocity.x, s.velocity.y, s.velocity.z));
+			}
+		}
+		Ok(())
+	}
+}
+
+// planes followed by the other static colliders
+fn static_colliders<'a>(planes: &'a [Plane], colliders: &'a [Box<StaticCollider>]) -> Vec<&'a StaticCollider> {
+	let mut all: Vec<&StaticCollider> = planes.iter().map(|plane| plane as &StaticCollider).collect();
+	all.extend(colliders.iter().map(|collider| &**collider));
+	all
+}
+
+// sleepers are left alone by still geometry, but one touching a kinematic collider is carried or
+// pushed by it, so it's woken even if the collider has just stopped or is moving away
+fn wake_if_carried(collider: &StaticCollider, sphere: & mut Sphere, dt: f32) {
+	if !sphere.sleeping || !collider.is_kinematic() {
+		return;
+	}
+	let moved = collider.velocity_at(sphere.position).norm() * dt;
+	if collider.touches(sphere, moved + 1.0e-3 * sphere.radius) {
+		sphere.wake();
+	}
+}
+
+fn is_ball(sphere: &Sphere) -> bool {
+	match sphere.shape {
+		Shape::Ball(_) => true,
+		_ => false,
+	}
+}
+
+// pairs rewound to their time of impact count as touching even if rounding left them a hair apart
+fn narrow_test(lhs: &Sphere, rhs: &Sphere, swept: bool) -> Option<CollisionResult> {
+	match hit_test(lhs, rhs) {
+		Some(result) => return Some(result),
+		None => (),
+	}
+	let offset = lhs.position - rhs.position;
+	let slack = 1.0e-3 * (lhs.radius + rhs.radius);
+	if swept && offset.norm() <= lhs.radius + rhs.radius + slack {
+		let normal = offset.normalize();
+		Some(CollisionResult {
+			normal: normal,
+			mtv: na::zero(),
+			point: rhs.position + normal * rhs.radius,
+		})
+	} else {
+		None
+	}
+}
+
+// moves each rewound island through the time left in the step at its resolved velocities, stopping at
+// every impact between its members on the way so pairs that meet later in the step don't pass through
+fn finish_step(bodies: & mut Vec<Sphere>, islands: &[Island], law: & VM) {
+	for island in islands.iter() {
+		let mut left = island.remaining;
+		for _ in 0..MAX_IMPACTS {
+			let (li, ri, time) = match first_impact(bodies, &island.bodies, left) {
+				Some(impact) => impact,
+				None => break,
+			};
+			advance(bodies, &island.bodies, time);
+			left -= time;
+			let (lhs, rhs) = bodies.get_pair_mut(li, ri);
+			match narrow_test(lhs, rhs, true) {
+				Some(result) => resolve_collision(lhs, rhs, result, law),
+				None => (),
+			}
+		}
+		advance(bodies, &island.bodies, left);
+	}
+}
+
+// the pair of balls in `island` that will touch first within `window` seconds, and when
+fn first_impact(bodies: &[Sphere], island: &[usize], window: f32) -> Option<(usize, usize, f32)> {
+	let mut first: Option<(usize, usize, f32)> = None;
+	for (n, &li) in island.iter().enumerate() {
+		for &ri in island[n + 1..].iter() {
+			let (lhs, rhs) = (&bodies[li], &bodies[ri]);
+			if !is_ball(lhs) || !is_ball(rhs) {
+				continue;
+			}
+			match time_until_impact(lhs, rhs, window) {
+				Some(time) if first.map_or(true, |(_, _, earliest)| time < earliest) => first = Some((li, ri, time)),
+				_ => (),
+			}
+		}
+	}
+	first
+}
+
+fn advance(bodies: & mut [Sphere], island: &[usize], time: f32) {
+	for &index in island.iter() {
+		let s = & mut bodies[index];
+		s.position = s.position + s.velocity * time;
+	}
+}
+
+// representative of the set `index` belongs to, halving paths on the way
+fn find_root(parent: & mut [usize], mut index: usize) -> usize {
+	while parent[index] != index {
+		parent[index] = parent[parent[index]];
+		index = parent[index];
+	}
+	index
+}
+
+// mutable access to the two spheres of a broadphase pair, which must be distinct
+fn sphere_pair_mut<'a>(bodies: &'a mut Vec<Sphere>, soft_bodies: &'a mut Vec<SoftBody>, lhs: BodyRef, rhs: BodyRef)
+	-> (&'a mut Sphere, &'a mut Sphere) {
+	match (lhs, rhs) {
+		(BodyRef::Body(l_index), BodyRef::Body(r_index)) => bodies.get_pair_mut(l_index, r_index),
+		(BodyRef::Body(body_index), BodyRef::Point(soft_index, point_index)) =>
+			(& mut bodies[body_index], & mut soft_bodies[soft_index].get_points_mut()[point_index]),
+		(BodyRef::Point(soft_index, point_index), BodyRef::Body(body_index)) =>
+			(& mut soft_bodies[soft_index].get_points_mut()[point_index], & mut bodies[body_index]),
+		(BodyRef::Point(l_soft, l_index), BodyRef::Point(r_soft, r_index)) => {
+			if l_soft == r_soft {
+				soft_bodies[l_soft].get_points_mut().get_pair_mut(l_index, r_index)
+			} else {
+				let (l_body, r_body) = soft_bodies.get_pair_mut(l_soft, r_soft);
+				(& mut l_body.get_points_mut()[l_index], & mut r_body.get_points_mut()[r_index])
+			}
+		},
+	}
+}